@@ -0,0 +1,9 @@
+-- the original queue table, left alone where it already exists
+CREATE TABLE IF NOT EXISTS `requests` (`id` int(11) NOT NULL AUTO_INCREMENT,
+`method` varchar(255) NOT NULL,
+`host` varchar(255) NOT NULL,
+`port` varchar(255) NOT NULL,
+`uri` varchar(255) NOT NULL,
+`headers` varchar(255) NOT NULL,
+`body` varchar(6255) NOT NULL,
+PRIMARY KEY (`id`));
@@ -0,0 +1,7 @@
+-- headers as JSON, bodies as bytes with their content type and encoding, the matched route
+ALTER TABLE `requests`
+MODIFY `headers` text NOT NULL,
+MODIFY `body` longblob NOT NULL,
+ADD `content_type` varchar(255) NULL,
+ADD `content_encoding` varchar(255) NULL,
+ADD `route` varchar(255) NULL;
@@ -0,0 +1,7 @@
+-- each key expires after the window of the route it was queued on
+CREATE TABLE `idempotency_keys` (`key_hash` char(64) NOT NULL,
+`request_id` int(11) NOT NULL,
+`created_at` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+`expires_at` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+PRIMARY KEY (`key_hash`),
+KEY `expires_at` (`expires_at`));
@@ -0,0 +1,5 @@
+-- workers claim a request before sending it
+ALTER TABLE `requests`
+ADD `claim_token` varchar(64) NULL,
+ADD `claimed_at` timestamp NULL,
+ADD KEY `claim_token` (`claim_token`);
@@ -0,0 +1,6 @@
+-- retry bookkeeping
+ALTER TABLE `requests`
+ADD `attempts` int(11) NOT NULL DEFAULT 0,
+ADD `last_error` text NULL,
+ADD `next_attempt_at` timestamp NULL,
+ADD KEY `next_attempt_at` (`next_attempt_at`);
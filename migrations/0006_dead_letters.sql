@@ -0,0 +1,23 @@
+ALTER TABLE `requests`
+ADD `destination` varchar(255) NULL,
+ADD `failure_history` text NULL;
+
+CREATE TABLE `dead_letters` (`id` int(11) NOT NULL AUTO_INCREMENT,
+`request_id` int(11) NOT NULL,
+`method` varchar(255) NOT NULL,
+`host` varchar(255) NOT NULL,
+`port` varchar(255) NOT NULL,
+`uri` varchar(255) NOT NULL,
+`headers` text NOT NULL,
+`body` longblob NOT NULL,
+`content_type` varchar(255) NULL,
+`content_encoding` varchar(255) NULL,
+`route` varchar(255) NULL,
+`destination` varchar(255) NULL,
+`attempts` int(11) NOT NULL DEFAULT 0,
+`failure_history` text NULL,
+`last_status` int(11) NULL,
+`last_response` text NULL,
+`dead_lettered_at` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+PRIMARY KEY (`id`),
+KEY `request_id` (`request_id`));
@@ -0,0 +1,4 @@
+-- claims expire so another instance can pick up the request
+ALTER TABLE `requests`
+ADD `lease_expires_at` timestamp NULL,
+ADD KEY `lease_expires_at` (`lease_expires_at`);
@@ -0,0 +1,10 @@
+-- effective_priority includes aging and is kept up to date by the dispatcher so claims can walk an index in order;
+-- descending index keys need MySQL 8.0+ or MariaDB 10.8+, older servers sort instead
+ALTER TABLE `requests`
+ADD `priority` int(11) NOT NULL DEFAULT 0,
+ADD `effective_priority` int(11) NOT NULL DEFAULT 0,
+ADD `created_at` timestamp NOT NULL DEFAULT CURRENT_TIMESTAMP,
+ADD KEY `effective_priority` (`effective_priority` DESC, `id`);
+
+ALTER TABLE `dead_letters`
+ADD `priority` int(11) NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+ALTER TABLE `requests`
+ADD `deliver_at` timestamp NULL,
+ADD KEY `deliver_at` (`deliver_at`);
+
+ALTER TABLE `dead_letters`
+ADD `deliver_at` timestamp NULL;
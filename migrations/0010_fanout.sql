@@ -0,0 +1,8 @@
+ALTER TABLE `requests`
+ADD `parent_id` int(11) NULL,
+ADD `fanout` tinyint(1) NOT NULL DEFAULT 0,
+ADD KEY `parent_id` (`parent_id`);
+
+ALTER TABLE `dead_letters`
+ADD `parent_id` int(11) NULL,
+ADD KEY `parent_id` (`parent_id`);
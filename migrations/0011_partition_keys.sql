@@ -0,0 +1,6 @@
+ALTER TABLE `requests`
+ADD `partition_key` char(64) NULL,
+ADD KEY `partition_key` (`partition_key`, `id`);
+
+ALTER TABLE `dead_letters`
+ADD `partition_key` char(64) NULL;
@@ -0,0 +1,17 @@
+CREATE TABLE `delivery_attempts` (`id` int(11) NOT NULL AUTO_INCREMENT,
+`request_id` int(11) NOT NULL,
+`attempt` int(11) NOT NULL,
+`destination` varchar(255) NOT NULL,
+`started_at` timestamp NULL,
+`finished_at` timestamp NULL,
+`latency_ms` bigint(20) NULL,
+`status` int(11) NULL,
+`outcome` varchar(32) NOT NULL,
+`error_kind` varchar(32) NULL,
+`error` text NULL,
+`response_headers` text NULL,
+`response_body` text NULL,
+PRIMARY KEY (`id`),
+KEY `request_id` (`request_id`, `id`),
+KEY `destination` (`destination`, `id`),
+KEY `started_at` (`started_at`));
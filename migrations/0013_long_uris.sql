@@ -0,0 +1,6 @@
+-- the stored uri includes the query string, which can run far past 255 characters
+ALTER TABLE `requests`
+MODIFY `uri` text NOT NULL;
+
+ALTER TABLE `dead_letters`
+MODIFY `uri` text NOT NULL;
@@ -0,0 +1,143 @@
+use crate::fairings::apikey_fairing::ApiKey;
+use crate::manage_requests::dead_letter_funcs;
+use crate::routing::body_limits::STORAGE_MAX_BODY_BYTES;
+use crate::routing::destinations::{DestinationConfig, DEFAULT_DESTINATION};
+use crate::{Accepted, ErrorResponder};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use log::info;
+use rocket::data::{Data, ToByteUnit};
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+
+// optional edits applied when a dead letter is requeued
+#[derive(Debug, Deserialize)]
+pub struct RequeueEdit {
+    body: Option<String>,
+    body_base64: Option<String>,
+    destination: Option<String>, // name of a [destinations.<name>] table
+}
+
+#[get("/reque/dead-letters?<limit>&<offset>")]
+pub async fn list_dead_letters(
+    _api_key: ApiKey<'_>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    let dead_letters = dead_letter_funcs::list_dead_letters(
+        limit.unwrap_or(100).clamp(1, 1000),
+        offset.unwrap_or(0).max(0),
+        pool,
+    )
+    .await?;
+    Ok(Json(json!({
+        "dead_letters": dead_letters,
+    })))
+}
+
+#[get("/reque/dead-letters/<id>")]
+pub async fn get_dead_letter(
+    _api_key: ApiKey<'_>,
+    id: i64,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    match dead_letter_funcs::get_dead_letter(id, pool).await? {
+        Some(dead_letter) => Ok(Json(dead_letter.detail())),
+        None => Err(ErrorResponder::with_status(
+            Status::NotFound,
+            "no such dead letter",
+        )),
+    }
+}
+
+#[post("/reque/dead-letters/<id>/requeue", data = "<edit>")]
+pub async fn requeue_dead_letter(
+    _api_key: ApiKey<'_>,
+    id: i64,
+    edit: Data<'_>,
+    pool: &rocket::State<MySqlPool>,
+    destinations: &rocket::State<HashMap<String, DestinationConfig>>,
+) -> Result<Accepted, ErrorResponder> {
+    let (body, body_base64, destination) = match read_edit(edit).await? {
+        Some(edit) => (edit.body, edit.body_base64, edit.destination),
+        None => (None, None, None),
+    };
+
+    let body = match (body, body_base64) {
+        (Some(body), _) => Some(body.into_bytes()),
+        (None, Some(encoded)) => Some(STANDARD.decode(encoded).map_err(|_| {
+            ErrorResponder::with_status(Status::BadRequest, "body_base64 is not valid base64")
+        })?),
+        (None, None) => None,
+    };
+    if let Some(destination) = &destination {
+        if destination != DEFAULT_DESTINATION && !destinations.contains_key(destination) {
+            return Err(ErrorResponder::with_status(
+                Status::BadRequest,
+                "no such destination",
+            ));
+        }
+    }
+
+    match dead_letter_funcs::requeue_dead_letter(id, body, destination, pool).await? {
+        Some(request_id) => {
+            info!(target:"app::requests", "dead letter {} requeued as request {}", id, request_id);
+            Ok(Accepted::new(request_id, false))
+        }
+        None => Err(ErrorResponder::with_status(
+            Status::NotFound,
+            "no such dead letter",
+        )),
+    }
+}
+
+// an empty body requeues the dead letter as it is, anything else has to be a valid edit,
+// so an edit is never dropped and the original payload requeued in its place
+async fn read_edit(edit: Data<'_>) -> Result<Option<RequeueEdit>, ErrorResponder> {
+    let edit = edit
+        .open(STORAGE_MAX_BODY_BYTES.bytes())
+        .into_bytes()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if !edit.is_complete() {
+        return Err(ErrorResponder::with_status(
+            Status::PayloadTooLarge,
+            &format!("edit exceeds the {} byte limit", STORAGE_MAX_BODY_BYTES),
+        ));
+    }
+    if edit.iter().all(u8::is_ascii_whitespace) {
+        return Ok(None);
+    }
+    serde_json::from_slice(&edit).map(Some).map_err(|e| {
+        ErrorResponder::with_status(Status::BadRequest, &format!("invalid edit - {}", e))
+    })
+}
+
+#[delete("/reque/dead-letters/<id>")]
+pub async fn purge_dead_letter(
+    _api_key: ApiKey<'_>,
+    id: i64,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    match dead_letter_funcs::purge_dead_letter(id, pool).await? {
+        true => Ok(Json(json!({ "purged": 1 }))),
+        false => Err(ErrorResponder::with_status(
+            Status::NotFound,
+            "no such dead letter",
+        )),
+    }
+}
+
+#[delete("/reque/dead-letters")]
+pub async fn purge_dead_letters(
+    _api_key: ApiKey<'_>,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    let purged = dead_letter_funcs::purge_dead_letters(pool).await?;
+    Ok(Json(json!({ "purged": purged })))
+}
@@ -0,0 +1,44 @@
+use crate::fairings::apikey_fairing::ApiKey;
+use crate::manage_requests::delivery_attempt_funcs;
+use crate::ErrorResponder;
+use rocket::http::Status;
+use rocket::serde::json::Json;
+use serde_json::{json, Value};
+use sqlx::MySqlPool;
+
+#[get("/reque/delivery-attempts?<request_id>&<destination>&<limit>&<offset>")]
+pub async fn list_delivery_attempts(
+    _api_key: ApiKey<'_>,
+    request_id: Option<i64>,
+    destination: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    let attempts = delivery_attempt_funcs::list_delivery_attempts(
+        request_id,
+        destination.as_deref(),
+        limit.unwrap_or(100).clamp(1, 1000),
+        offset.unwrap_or(0).max(0),
+        pool,
+    )
+    .await?;
+    Ok(Json(json!({
+        "delivery_attempts": attempts.iter().map(|a| a.to_json()).collect::<Vec<_>>(),
+    })))
+}
+
+#[get("/reque/delivery-attempts/<id>")]
+pub async fn get_delivery_attempt(
+    _api_key: ApiKey<'_>,
+    id: i64,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<Value>, ErrorResponder> {
+    match delivery_attempt_funcs::get_delivery_attempt(id, pool).await? {
+        Some(attempt) => Ok(Json(attempt.to_json())),
+        None => Err(ErrorResponder::with_status(
+            Status::NotFound,
+            "no such delivery attempt",
+        )),
+    }
+}
@@ -0,0 +1,24 @@
+use crate::dispatcher::circuit_breaker::CircuitBreakers;
+use crate::fairings::apikey_fairing::ApiKey;
+use rocket::serde::json::Json;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+// circuit breaker state of every destination
+#[get("/reque/destinations")]
+pub async fn list_destinations(
+    _api_key: ApiKey<'_>,
+    breakers: &rocket::State<Arc<CircuitBreakers>>,
+) -> Json<Value> {
+    let destinations = breakers
+        .iter()
+        .map(|(name, breaker)| {
+            (
+                name.clone(),
+                json!({ "circuit_breaker": breaker.snapshot() }),
+            )
+        })
+        .collect::<BTreeMap<String, Value>>();
+    Json(json!({ "destinations": destinations }))
+}
@@ -0,0 +1,3 @@
+pub mod dead_letters;
+pub mod delivery_attempts;
+pub mod destinations;
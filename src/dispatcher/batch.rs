@@ -0,0 +1,272 @@
+use crate::entities::delivery_result::{DeliveryResult, RESPONSE_BODY_LIMIT};
+use crate::entities::storedrequest::StoredRequest;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// batch responses are read this far so per-item results can be parsed
+pub const BATCH_RESPONSE_LIMIT: usize = 1024 * 1024;
+
+// [destinations.<name>.batch] table in config/Settings.toml
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct BatchConfig {
+    pub max_size: usize,  // requests sent together in one call
+    pub max_wait_ms: u64, // how long a partial batch waits for more requests
+    pub envelope: Envelope,
+    pub path: Option<String>, // path and query of the bulk endpoint, the first request's uri when unset
+    pub method: String,
+    pub item_results: Option<String>, // dotted path of a per-item results array in the response, "" for a top level array
+}
+
+impl Default for BatchConfig {
+    fn default() -> Self {
+        BatchConfig {
+            max_size: 50,
+            max_wait_ms: 1000,
+            envelope: Envelope::JsonArray,
+            path: None,
+            method: "POST".to_string(),
+            item_results: None,
+        }
+    }
+}
+
+// how the bodies of a batch are combined into one request body
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Envelope {
+    JsonArray,
+    Ndjson,
+}
+
+impl Envelope {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            Envelope::JsonArray => "application/json",
+            Envelope::Ndjson => "application/x-ndjson",
+        }
+    }
+
+    pub fn build(&self, members: &[&StoredRequest]) -> Vec<u8> {
+        let items = members
+            .iter()
+            .map(|member| item(member))
+            .collect::<Vec<Value>>();
+        match self {
+            Envelope::JsonArray => Value::Array(items).to_string().into_bytes(),
+            Envelope::Ndjson => items
+                .iter()
+                .map(|item| format!("{}\n", item))
+                .collect::<String>()
+                .into_bytes(),
+        }
+    }
+}
+
+// JSON bodies are embedded as they are and other text as a JSON string; bodies that are not UTF-8
+// or have a content encoding are embedded base64 encoded, so they arrive byte for byte, as
+// {"body_base64": ..., "content_type": ..., "content_encoding": ...}
+fn item(member: &StoredRequest) -> Value {
+    let encoded = member
+        .content_encoding
+        .as_deref()
+        .is_some_and(|e| !e.trim().eq_ignore_ascii_case("identity"));
+    if !encoded {
+        if let Ok(json) = serde_json::from_slice::<Value>(&member.body) {
+            return json;
+        }
+        if let Ok(text) = std::str::from_utf8(&member.body) {
+            return Value::String(text.to_string());
+        }
+    }
+    json!({
+        "body_base64": STANDARD.encode(&member.body),
+        "content_type": member.content_type,
+        "content_encoding": member.content_encoding,
+    })
+}
+
+impl BatchConfig {
+    // result of each member, in order; the batch result applies to members the response does not
+    // report on, and per-item entries are either a status code or an object with a "status" field
+    pub fn member_results(&self, batch: &DeliveryResult, count: usize) -> Vec<DeliveryResult> {
+        let shared = DeliveryResult {
+            response_body: batch.response_body.as_deref().map(truncate),
+            ..batch.clone()
+        };
+        let items = match (&self.item_results, &batch.response_body, batch.status) {
+            (Some(path), Some(body), Some(_)) => item_results(body, path),
+            _ => Vec::new(),
+        };
+        (0..count)
+            .map(|i| {
+                let Some(item) = items.get(i) else {
+                    return shared.clone();
+                };
+                let status = match item {
+                    Value::Number(n) => n.as_u64(),
+                    item => item.get("status").and_then(Value::as_u64),
+                };
+                DeliveryResult {
+                    status: status.and_then(|s| u16::try_from(s).ok()).or(batch.status),
+                    response_body: Some(truncate(&item.to_string())),
+                    ..batch.clone()
+                }
+            })
+            .collect()
+    }
+}
+
+fn item_results(body: &str, path: &str) -> Vec<Value> {
+    let Ok(mut value) = serde_json::from_str::<Value>(body) else {
+        return Vec::new();
+    };
+    for key in path.split('.').filter(|k| !k.is_empty()) {
+        value = value.get_mut(key).map(Value::take).unwrap_or_default();
+    }
+    match value {
+        Value::Array(items) => items,
+        _ => Vec::new(),
+    }
+}
+
+fn truncate(body: &str) -> String {
+    if body.len() <= RESPONSE_BODY_LIMIT {
+        return body.to_string();
+    }
+    String::from_utf8_lossy(&body.as_bytes()[..RESPONSE_BODY_LIMIT]).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(body: &[u8], content_encoding: Option<&str>) -> StoredRequest {
+        StoredRequest {
+            method: "POST".to_string(),
+            host: "localhost".to_string(),
+            port: 80,
+            uri: "/events".to_string(),
+            headers: Default::default(),
+            body: body.to_vec(),
+            content_type: Some("application/octet-stream".to_string()),
+            content_encoding: content_encoding.map(str::to_string),
+            route: None,
+            destination: None,
+            attempts: 0,
+            priority: 0,
+            deliver_at: None,
+            parent_id: None,
+            partition_key: None,
+        }
+    }
+
+    fn batch(item_results: Option<&str>) -> BatchConfig {
+        BatchConfig {
+            item_results: item_results.map(str::to_string),
+            ..Default::default()
+        }
+    }
+
+    fn response(status: u16, body: &str) -> DeliveryResult {
+        DeliveryResult {
+            status: Some(status),
+            response_body: Some(body.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn statuses(results: &[DeliveryResult]) -> Vec<Option<u16>> {
+        results.iter().map(|r| r.status).collect()
+    }
+
+    #[test]
+    fn embeds_json_and_text_bodies() {
+        let json = member(br#"{"a":1}"#, None);
+        let text = member(b"a=1&b=2", Some("identity"));
+        let body = Envelope::JsonArray.build(&[&json, &text]);
+        assert_eq!(body, br#"[{"a":1},"a=1&b=2"]"#);
+        let body = Envelope::Ndjson.build(&[&json, &text]);
+        assert_eq!(body, b"{\"a\":1}\n\"a=1&b=2\"\n");
+    }
+
+    #[test]
+    fn base64_encodes_binary_and_encoded_bodies() {
+        let binary = member(&[0xff, 0x00, 0x80], None);
+        let gzipped = member(b"{}", Some("gzip"));
+        let body = Envelope::JsonArray.build(&[&binary, &gzipped]);
+        let items: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            items,
+            json!([
+                {
+                    "body_base64": "/wCA",
+                    "content_type": "application/octet-stream",
+                    "content_encoding": null,
+                },
+                {
+                    "body_base64": "e30=",
+                    "content_type": "application/octet-stream",
+                    "content_encoding": "gzip",
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn finds_item_results_by_dotted_path() {
+        let body = r#"{"data":{"results":[200,{"status":500}]}}"#;
+        assert_eq!(item_results(body, "data.results").len(), 2);
+        assert_eq!(item_results("[1,2,3]", "").len(), 3);
+        assert!(item_results(body, "data.missing").is_empty());
+        assert!(item_results(body, "data").is_empty(), "not an array");
+        assert!(item_results("not json", "").is_empty());
+    }
+
+    #[test]
+    fn applies_the_batch_result_without_item_results() {
+        let results = batch(None).member_results(&response(502, "bad gateway"), 3);
+        assert_eq!(statuses(&results), vec![Some(502); 3]);
+        assert!(results
+            .iter()
+            .all(|r| r.response_body.as_deref() == Some("bad gateway")));
+    }
+
+    #[test]
+    fn maps_item_statuses_to_members_in_order() {
+        let body = r#"{"results":[201,{"status":422,"error":"invalid"},{"id":7}]}"#;
+        let results = batch(Some("results")).member_results(&response(207, body), 4);
+        assert_eq!(
+            statuses(&results),
+            vec![Some(201), Some(422), Some(207), Some(207)],
+            "entries without a status and missing entries take the batch status"
+        );
+        assert_eq!(
+            results[1].response_body.as_deref(),
+            Some(r#"{"error":"invalid","status":422}"#)
+        );
+        assert_eq!(results[3].response_body.as_deref(), Some(body));
+    }
+
+    #[test]
+    fn ignores_item_results_without_a_response() {
+        let failed = DeliveryResult {
+            error: Some("connection refused".to_string()),
+            ..Default::default()
+        };
+        let results = batch(Some("")).member_results(&failed, 2);
+        assert_eq!(statuses(&results), vec![None, None]);
+    }
+
+    #[test]
+    fn truncates_response_bodies() {
+        let body = format!("[{}]", "1,".repeat(RESPONSE_BODY_LIMIT) + "1");
+        let results = batch(None).member_results(&response(200, &body), 1);
+        assert_eq!(
+            results[0].response_body.as_ref().map(String::len),
+            Some(RESPONSE_BODY_LIMIT)
+        );
+    }
+}
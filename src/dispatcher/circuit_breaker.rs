@@ -0,0 +1,367 @@
+use log::{info, warn};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// breakers keyed by destination name, shared between the dispatcher and the admin endpoint
+pub type CircuitBreakers = HashMap<String, CircuitBreaker>;
+
+// [circuit_breaker] table in config/Settings.toml, or [destinations.<name>.circuit_breaker] to replace it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CircuitBreakerConfig {
+    pub enabled: bool,
+    pub consecutive_failures: u32, // trip after this many retryable failures in a row
+    pub failure_rate: f64,         // or when this share of the recent window failed
+    pub window: usize,             // recent deliveries the failure rate is computed over
+    pub min_requests: usize,       // deliveries needed in the window before the rate applies
+    pub open_secs: u64,            // pause before a single probe request is let through
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        CircuitBreakerConfig {
+            enabled: true,
+            consecutive_failures: 5,
+            failure_rate: 0.5,
+            window: 20,
+            min_requests: 10,
+            open_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { probing: bool },
+}
+
+struct Inner {
+    state: State,
+    consecutive_failures: u32,
+    recent: VecDeque<bool>, // true for a failure
+    opened_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+// closed lets everything through, open pauses the destination,
+// half-open lets a single probe through and closes again when it succeeds
+pub struct CircuitBreaker {
+    name: String,
+    config: CircuitBreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+impl CircuitBreaker {
+    pub fn new(name: &str, config: CircuitBreakerConfig) -> CircuitBreaker {
+        CircuitBreaker {
+            name: name.to_string(),
+            config,
+            inner: Mutex::new(Inner {
+                state: State::Closed,
+                consecutive_failures: 0,
+                recent: VecDeque::new(),
+                opened_at: None,
+            }),
+        }
+    }
+
+    // whether workers should leave this destination's requests in the queue for now
+    pub fn paused(&self) -> bool {
+        let inner = self.inner.lock().unwrap();
+        match inner.state {
+            State::Closed => false,
+            State::Open { until } => Instant::now() < until,
+            State::HalfOpen { probing } => probing,
+        }
+    }
+
+    // permission to send one request, none while the breaker is open or a probe is in flight
+    pub fn try_acquire(&self) -> Option<BreakerPermit<'_>> {
+        if !self.config.enabled {
+            return Some(BreakerPermit::new(self, false));
+        }
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            State::Closed => Some(BreakerPermit::new(self, false)),
+            State::Open { until } if Instant::now() < until => None,
+            State::Open { .. } | State::HalfOpen { probing: false } => {
+                info!(
+                    "circuit breaker for {} is half-open, sending a probe",
+                    self.name
+                );
+                inner.state = State::HalfOpen { probing: true };
+                Some(BreakerPermit::new(self, true))
+            }
+            State::HalfOpen { probing: true } => None,
+        }
+    }
+
+    // seconds until an open breaker lets a probe through
+    pub fn retry_after_secs(&self) -> u64 {
+        match self.inner.lock().unwrap().state {
+            State::Open { until } => until
+                .saturating_duration_since(Instant::now())
+                .as_secs()
+                .max(1),
+            _ => 1,
+        }
+    }
+
+    fn record(&self, failed: bool, probe: bool) {
+        if !self.config.enabled {
+            return;
+        }
+        let mut inner = self.inner.lock().unwrap();
+        if probe {
+            if failed {
+                self.open(&mut inner, "probe failed");
+            } else {
+                info!("circuit breaker for {} closed, probe succeeded", self.name);
+                inner.state = State::Closed;
+                inner.consecutive_failures = 0;
+                inner.recent.clear();
+                inner.opened_at = None;
+            }
+            return;
+        }
+        if inner.state != State::Closed {
+            return;
+        }
+
+        inner.consecutive_failures = if failed {
+            inner.consecutive_failures + 1
+        } else {
+            0
+        };
+        inner.recent.push_back(failed);
+        while inner.recent.len() > self.config.window.max(1) {
+            inner.recent.pop_front();
+        }
+        let failures = inner.recent.iter().filter(|f| **f).count();
+        let rate = failures as f64 / inner.recent.len() as f64;
+
+        if inner.consecutive_failures >= self.config.consecutive_failures.max(1) {
+            let reason = format!("{} consecutive failures", inner.consecutive_failures);
+            self.open(&mut inner, &reason);
+        } else if inner.recent.len() >= self.config.min_requests && rate >= self.config.failure_rate
+        {
+            let reason = format!(
+                "{} of the last {} deliveries failed",
+                failures,
+                inner.recent.len()
+            );
+            self.open(&mut inner, &reason);
+        }
+    }
+
+    fn open(&self, inner: &mut Inner, reason: &str) {
+        warn!(
+            "circuit breaker for {} opened, {} - pausing delivery for {}s",
+            self.name, reason, self.config.open_secs
+        );
+        inner.state = State::Open {
+            until: Instant::now() + Duration::from_secs(self.config.open_secs),
+        };
+        inner.opened_at = Some(chrono::Utc::now());
+    }
+
+    // a probe that never reported back lets the next request probe instead
+    fn abandon_probe(&self) {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == (State::HalfOpen { probing: true }) {
+            inner.state = State::HalfOpen { probing: false };
+        }
+    }
+
+    pub fn snapshot(&self) -> Value {
+        let inner = self.inner.lock().unwrap();
+        let failures = inner.recent.iter().filter(|f| **f).count();
+        let (state, retry_in_secs) = match inner.state {
+            State::Closed => ("closed", None),
+            State::Open { until } => (
+                "open",
+                Some(until.saturating_duration_since(Instant::now()).as_secs()),
+            ),
+            State::HalfOpen { .. } => ("half_open", None),
+        };
+        json!({
+            "enabled": self.config.enabled,
+            "state": state,
+            "consecutive_failures": inner.consecutive_failures,
+            "recent_failures": failures,
+            "recent_deliveries": inner.recent.len(),
+            "opened_at": inner.opened_at.map(|t| t.to_rfc3339()),
+            "probe_in_secs": retry_in_secs,
+        })
+    }
+}
+
+// held for one delivery, the outcome must be recorded for the breaker to learn from it
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    recorded: bool,
+}
+
+impl<'a> BreakerPermit<'a> {
+    fn new(breaker: &'a CircuitBreaker, probe: bool) -> BreakerPermit<'a> {
+        BreakerPermit {
+            breaker,
+            probe,
+            recorded: false,
+        }
+    }
+
+    pub fn record(mut self, failed: bool) {
+        self.recorded = true;
+        self.breaker.record(failed, self.probe);
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if self.probe && !self.recorded {
+            self.breaker.abandon_probe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn breaker(consecutive_failures: u32, min_requests: usize, open_secs: u64) -> CircuitBreaker {
+        CircuitBreaker::new(
+            "test",
+            CircuitBreakerConfig {
+                enabled: true,
+                consecutive_failures,
+                failure_rate: 0.5,
+                window: 4,
+                min_requests,
+                open_secs,
+            },
+        )
+    }
+
+    fn state(breaker: &CircuitBreaker) -> Value {
+        breaker.snapshot()["state"].clone()
+    }
+
+    fn deliver(breaker: &CircuitBreaker, failed: bool) {
+        breaker.try_acquire().unwrap().record(failed);
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = breaker(3, 100, 30);
+        deliver(&breaker, true);
+        deliver(&breaker, true);
+        deliver(&breaker, false);
+        deliver(&breaker, true);
+        deliver(&breaker, true);
+        assert_eq!(state(&breaker), "closed");
+        assert!(!breaker.paused());
+
+        deliver(&breaker, true);
+        assert_eq!(state(&breaker), "open");
+        assert!(breaker.paused());
+        assert!(breaker.try_acquire().is_none());
+        assert!(breaker.retry_after_secs() > 0);
+    }
+
+    #[test]
+    fn opens_when_the_window_failure_rate_is_reached() {
+        let breaker = breaker(100, 4, 30);
+        deliver(&breaker, false);
+        deliver(&breaker, true);
+        deliver(&breaker, false);
+        assert_eq!(
+            state(&breaker),
+            "closed",
+            "fewer deliveries than min_requests"
+        );
+
+        deliver(&breaker, true);
+        assert_eq!(state(&breaker), "open");
+    }
+
+    #[test]
+    fn failure_rate_only_counts_the_window() {
+        let breaker = breaker(100, 4, 30);
+        for failed in [true, false, false, false, false, true] {
+            deliver(&breaker, failed);
+        }
+        assert_eq!(state(&breaker), "closed");
+        assert_eq!(breaker.snapshot()["recent_deliveries"], 4);
+        assert_eq!(breaker.snapshot()["recent_failures"], 1);
+    }
+
+    #[test]
+    fn lets_a_single_probe_through_once_the_pause_is_over() {
+        let breaker = breaker(1, 100, 0);
+        deliver(&breaker, true);
+        assert_eq!(state(&breaker), "open");
+
+        let probe = breaker.try_acquire().expect("probe after the pause");
+        assert_eq!(state(&breaker), "half_open");
+        assert!(breaker.paused());
+        assert!(breaker.try_acquire().is_none(), "one probe at a time");
+
+        probe.record(false);
+        assert_eq!(state(&breaker), "closed");
+        assert_eq!(breaker.snapshot()["consecutive_failures"], 0);
+        assert_eq!(breaker.snapshot()["recent_deliveries"], 0);
+        assert!(breaker.snapshot()["opened_at"].is_null());
+    }
+
+    #[test]
+    fn failed_probe_opens_again() {
+        let breaker = breaker(1, 100, 0);
+        deliver(&breaker, true);
+        breaker.try_acquire().unwrap().record(true);
+        assert_eq!(state(&breaker), "open");
+    }
+
+    #[test]
+    fn dropped_probe_lets_the_next_request_probe() {
+        let breaker = breaker(1, 100, 0);
+        deliver(&breaker, true);
+        drop(breaker.try_acquire().unwrap());
+        assert_eq!(state(&breaker), "half_open");
+        assert!(!breaker.paused());
+        assert!(breaker.try_acquire().is_some());
+    }
+
+    #[test]
+    fn ignores_permits_taken_before_opening() {
+        let breaker = breaker(1, 100, 30);
+        let tripping = breaker.try_acquire().unwrap();
+        let earlier = breaker.try_acquire().unwrap();
+        tripping.record(true);
+        earlier.record(false);
+        assert_eq!(state(&breaker), "open");
+        assert!(breaker.try_acquire().is_none());
+    }
+
+    #[test]
+    fn disabled_breaker_never_opens() {
+        let breaker = CircuitBreaker::new(
+            "test",
+            CircuitBreakerConfig {
+                enabled: false,
+                consecutive_failures: 1,
+                ..Default::default()
+            },
+        );
+        for _ in 0..10 {
+            deliver(&breaker, true);
+        }
+        assert_eq!(state(&breaker), "closed");
+        assert!(breaker.try_acquire().is_some());
+    }
+}
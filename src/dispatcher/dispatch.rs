@@ -0,0 +1,460 @@
+use crate::dispatcher::batch::BatchConfig;
+use crate::dispatcher::circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitBreakers};
+use crate::dispatcher::http_client::DestinationClients;
+use crate::dispatcher::outcome::{OutcomeAction, OutcomeClass, OutcomePolicy};
+use crate::dispatcher::rate_limit::DestinationLimiter;
+use crate::dispatcher::retry::RetryPolicy;
+use crate::entities::delivery_result::DeliveryResult;
+use crate::entities::storedrequest::StoredRequest;
+use crate::manage_requests::dead_letter_funcs::dead_letter_request;
+use crate::manage_requests::delivery_attempt_funcs::record_delivery_attempt;
+use crate::manage_requests::request_funcs::{
+    age_priorities, claim_batch_members, claim_next_request, delete_request_from_db, release_claim,
+    renew_lease, schedule_retry, send_batch, send_stored_request,
+};
+use crate::routing::destinations::{DestinationConfig, DEFAULT_DESTINATION};
+use crate::routing::route_table::RouteTable;
+use log::{debug, info, warn};
+use rocket::tokio;
+use rocket::tokio::time::{sleep, Duration, Instant};
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+// dispatcher settings from the top level of config/Settings.toml
+#[derive(Debug, Clone)]
+pub struct DispatcherConfig {
+    pub http_proto: String,
+    pub http_dest: String,
+    pub reque_interval: u64, // default pacing, one delivery per interval per destination
+    pub poll_interval_ms: u64, // how long an idle worker waits before looking for work again
+    pub workers: usize,
+    pub max_in_flight: usize, // default concurrent deliveries per destination
+    pub delivery_timeout_secs: u64, // default time allowed for a destination to respond
+    pub lease_secs: u64, // how long a claimed request stays hidden from other workers and replicas
+    pub priority_aging_secs: u64, // waiting time that raises a request by one priority level, 0 disables aging
+    pub attempt_retention_days: u64, // how long delivery attempts are kept, 0 keeps them forever
+    pub retry: RetryPolicy,
+    pub outcome: OutcomePolicy, // default outcome policy, from the [outcome] table
+    pub circuit_breaker: CircuitBreakerConfig, // default breaker, from the [circuit_breaker] table
+}
+
+impl DispatcherConfig {
+    pub fn from_settings(settings_map: &HashMap<String, String>) -> DispatcherConfig {
+        let workers = settings_map
+            .get("dispatch_workers")
+            .map(|w| w.parse::<usize>().expect("cannot parse dispatch_workers"))
+            .unwrap_or(1)
+            .max(1);
+        DispatcherConfig {
+            http_proto: settings_map.get("http_proto").unwrap().to_string(),
+            http_dest: settings_map.get("http_dest").unwrap().to_string(),
+            reque_interval: settings_map
+                .get("reque_interval")
+                .unwrap()
+                .parse::<u64>()
+                .unwrap(),
+            poll_interval_ms: settings_map
+                .get("poll_interval_ms")
+                .map(|p| p.parse::<u64>().expect("cannot parse poll_interval_ms"))
+                .unwrap_or(500),
+            workers,
+            max_in_flight: settings_map
+                .get("max_in_flight_per_destination")
+                .map(|m| {
+                    m.parse::<usize>()
+                        .expect("cannot parse max_in_flight_per_destination")
+                })
+                .unwrap_or(workers)
+                .max(1),
+            delivery_timeout_secs: settings_map
+                .get("delivery_timeout_secs")
+                .map(|t| {
+                    t.parse::<u64>()
+                        .expect("cannot parse delivery_timeout_secs")
+                })
+                .unwrap_or(30)
+                .max(1),
+            lease_secs: settings_map
+                .get("claim_lease_secs")
+                .map(|l| l.parse::<u64>().expect("cannot parse claim_lease_secs"))
+                .unwrap_or(300)
+                .max(1),
+            priority_aging_secs: settings_map
+                .get("priority_aging_secs")
+                .map(|a| a.parse::<u64>().expect("cannot parse priority_aging_secs"))
+                .unwrap_or(60),
+            attempt_retention_days: settings_map
+                .get("delivery_attempt_retention_days")
+                .map(|d| {
+                    d.parse::<u64>()
+                        .expect("cannot parse delivery_attempt_retention_days")
+                })
+                .unwrap_or(7),
+            retry: RetryPolicy::from_settings(settings_map),
+            outcome: OutcomePolicy::default(),
+            circuit_breaker: CircuitBreakerConfig::default(),
+        }
+    }
+}
+
+// pool of workers that claim queued requests and deliver them to their destination
+pub struct Dispatcher {
+    config: DispatcherConfig,
+    pool: MySqlPool,
+    routes: RouteTable,
+    destinations: HashMap<String, DestinationConfig>,
+    limiters: HashMap<String, DestinationLimiter>,
+    breakers: Arc<CircuitBreakers>,
+    clients: Arc<DestinationClients>,
+}
+
+impl Dispatcher {
+    pub fn new(
+        config: DispatcherConfig,
+        pool: MySqlPool,
+        routes: RouteTable,
+        mut destinations: HashMap<String, DestinationConfig>,
+        clients: Arc<DestinationClients>,
+    ) -> Arc<Dispatcher> {
+        destinations
+            .entry(DEFAULT_DESTINATION.to_string())
+            .or_default();
+        let limiters = destinations
+            .iter()
+            .map(|(name, destination)| {
+                let rate = destination
+                    .rate
+                    .unwrap_or(1.0 / config.reque_interval.max(1) as f64);
+                let burst = destination.burst.unwrap_or(1);
+                let max_concurrency = destination.max_concurrency.unwrap_or(config.max_in_flight);
+                info!(
+                    "destination {} - {} requests per second{}, burst {}, at most {} in flight",
+                    name,
+                    rate,
+                    if destination.adaptive.is_some() {
+                        " to start, adaptive"
+                    } else {
+                        ""
+                    },
+                    burst,
+                    max_concurrency
+                );
+                (
+                    name.clone(),
+                    DestinationLimiter::new(
+                        name,
+                        rate,
+                        burst,
+                        max_concurrency,
+                        destination.adaptive.clone(),
+                    ),
+                )
+            })
+            .collect();
+        let breakers = destinations
+            .iter()
+            .map(|(name, destination)| {
+                let breaker = destination
+                    .circuit_breaker
+                    .clone()
+                    .unwrap_or_else(|| config.circuit_breaker.clone());
+                (name.clone(), CircuitBreaker::new(name, breaker))
+            })
+            .collect();
+
+        Arc::new(Dispatcher {
+            config,
+            pool,
+            routes,
+            destinations,
+            limiters,
+            breakers: Arc::new(breakers),
+            clients,
+        })
+    }
+
+    // breaker state for the admin endpoint
+    pub fn circuit_breakers(&self) -> Arc<CircuitBreakers> {
+        self.breakers.clone()
+    }
+
+    // start the configured number of workers
+    pub async fn spawn(self: Arc<Self>) {
+        // claims left behind by a crashed worker or replica are picked up again once their lease expires
+        let longest_timeout = self
+            .destinations
+            .values()
+            .filter_map(|d| d.timeout_secs)
+            .chain([self.config.delivery_timeout_secs])
+            .max()
+            .unwrap_or_default();
+        if longest_timeout >= self.config.lease_secs {
+            warn!(
+                "claim_lease_secs {} does not exceed the longest delivery timeout {}, slow deliveries may be sent twice",
+                self.config.lease_secs, longest_timeout
+            );
+        }
+
+        info!("starting {} dispatch workers", self.config.workers);
+        for worker in 0..self.config.workers {
+            let dispatcher = self.clone();
+            tokio::spawn(async move { dispatcher.supervise_worker(worker).await });
+        }
+        if self.config.priority_aging_secs > 0 {
+            let dispatcher = self.clone();
+            tokio::spawn(async move { dispatcher.run_aging().await });
+        }
+    }
+
+    // raise the effective priority of waiting requests once per aging interval
+    async fn run_aging(&self) {
+        let aging_secs = self.config.priority_aging_secs;
+        loop {
+            sleep(Duration::from_secs(aging_secs)).await;
+            match age_priorities(aging_secs, &self.pool).await {
+                Ok(0) => {}
+                Ok(aged) => info!("raised the priority of {} waiting requests", aged),
+                Err(e) => warn!("could not age request priorities - {}", e),
+            }
+        }
+    }
+
+    // restart a worker whose task panicked, so one bad delivery never stops dispatch for good
+    async fn supervise_worker(self: Arc<Self>, worker: usize) {
+        loop {
+            let dispatcher = self.clone();
+            let stopped = tokio::spawn(async move { dispatcher.run_worker(worker).await }).await;
+            if let Err(e) = stopped {
+                warn!("dispatch worker {} stopped ({}), restarting it", worker, e);
+            }
+            sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+        }
+    }
+
+    async fn run_worker(&self, worker: usize) {
+        loop {
+            // claim-then-send, the claim token keeps the row away from every other worker
+            let claim_token = Uuid::new_v4().to_string();
+            // skip destinations that could not take the request now, so no worker sits on a claimed
+            // row waiting for a slot or token while other destinations' queues wait
+            let paused: Vec<String> = self
+                .breakers
+                .iter()
+                .filter(|(name, breaker)| breaker.paused() || !self.limiters[*name].ready())
+                .map(|(name, _)| name.clone())
+                .collect();
+            let Some(first) =
+                claim_next_request(&claim_token, self.config.lease_secs, &paused, &self.pool).await
+            else {
+                sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+                continue;
+            };
+            let destination = self.destination_name(&first.1).to_string();
+            let batch = self
+                .destinations
+                .get(&destination)
+                .and_then(|d| d.batch.as_ref());
+            let mut members = vec![first];
+            if let Some(batch) = batch {
+                self.fill_batch(&claim_token, &destination, batch, &mut members)
+                    .await;
+            }
+
+            // another worker may have opened the breaker or started a probe since the claim
+            let breaker = &self.breakers[destination.as_str()];
+            let Some(breaker_permit) = breaker.try_acquire() else {
+                for (id, _) in &members {
+                    if let Err(e) =
+                        release_claim(*id, &claim_token, breaker.retry_after_secs(), &self.pool)
+                            .await
+                    {
+                        warn!(target:"app::requests", "could not release request {}, it is retried once its lease expires - {}", id, e);
+                    }
+                }
+                continue;
+            };
+
+            // wait for the destination's rate limit and a free in-flight slot, a batch is one call
+            let limiter = &self.limiters[destination.as_str()];
+            let _permit = limiter.acquire().await;
+
+            // waiting on the limiter may outlast the lease, so renew it and skip requests that were lost
+            let mut live = Vec::with_capacity(members.len());
+            for (id, stored) in members {
+                if renew_lease(id, &claim_token, self.config.lease_secs, &self.pool).await {
+                    debug!(target:"app::requests", "worker {} - {} - priority {} - {} {} {}", worker, id, stored.priority, stored.method, stored.host, stored.uri);
+                    live.push((id, stored));
+                } else {
+                    warn!(target:"app::requests", "lease on request {} expired before sending, leaving it to its new claimer", id);
+                }
+            }
+            let class = match (batch, live.first()) {
+                (_, None) => continue,
+                (Some(batch), _) => {
+                    self.deliver_batch(&claim_token, &destination, batch, &live)
+                        .await
+                }
+                (None, Some((id, stored))) => self.deliver(*id, &claim_token, stored).await,
+            };
+            breaker_permit.record(class == OutcomeClass::Retryable);
+        }
+    }
+
+    // claim more requests for the destination until the batch is full or max_wait_ms has passed
+    async fn fill_batch(
+        &self,
+        claim_token: &str,
+        destination: &str,
+        batch: &BatchConfig,
+        members: &mut Vec<(i64, StoredRequest)>,
+    ) {
+        let max_size = batch.max_size.max(1);
+        let deadline = Instant::now() + Duration::from_millis(batch.max_wait_ms);
+        while members.len() < max_size {
+            let claimed = claim_batch_members(
+                claim_token,
+                self.config.lease_secs,
+                destination,
+                max_size - members.len(),
+                &self.pool,
+            )
+            .await;
+            let found = !claimed.is_empty();
+            members.extend(claimed);
+            let now = Instant::now();
+            if members.len() >= max_size || now >= deadline {
+                break;
+            }
+            if !found {
+                sleep(Duration::from_millis(self.config.poll_interval_ms).min(deadline - now))
+                    .await;
+            }
+        }
+    }
+
+    // requests without a destination, or naming one that is no longer configured, go to the default
+    fn destination_name<'a>(&self, stored: &'a StoredRequest) -> &'a str {
+        match stored.destination.as_deref() {
+            Some(name) if self.destinations.contains_key(name) => name,
+            _ => DEFAULT_DESTINATION,
+        }
+    }
+
+    fn base_url(&self, destination: &str) -> String {
+        match self
+            .destinations
+            .get(destination)
+            .and_then(|d| d.url.as_ref())
+        {
+            Some(url) => url.trim_end_matches('/').to_string(),
+            None => format!("{}://{}", self.config.http_proto, self.config.http_dest),
+        }
+    }
+
+    async fn deliver(&self, id: i64, claim_token: &str, stored: &StoredRequest) -> OutcomeClass {
+        let route = stored
+            .route
+            .as_deref()
+            .and_then(|name| self.routes.get(name));
+        let client = &self.clients[self.destination_name(stored)];
+
+        let base_url = self.base_url(self.destination_name(stored));
+        let result = send_stored_request(client, &base_url, stored, route).await;
+
+        let class = self
+            .outcome(self.destination_name(stored))
+            .classify(&result);
+        self.limiters[self.destination_name(stored)]
+            .observe(&result, class == OutcomeClass::Retryable);
+        self.finish(id, claim_token, stored, &result).await
+    }
+
+    // one call for the whole batch, each member is then deleted, retried or dead lettered on its own
+    // result; the batch result stands in for members the response has no per-item result for
+    async fn deliver_batch(
+        &self,
+        claim_token: &str,
+        destination: &str,
+        batch: &BatchConfig,
+        members: &[(i64, StoredRequest)],
+    ) -> OutcomeClass {
+        let client = &self.clients[destination];
+        let stored: Vec<&StoredRequest> = members.iter().map(|(_, stored)| stored).collect();
+        let result = send_batch(client, &self.base_url(destination), batch, &stored).await;
+
+        let class = self.outcome(destination).classify(&result);
+        self.limiters[destination].observe(&result, class == OutcomeClass::Retryable);
+        info!(target:"app::requests", "batch of {} requests to {} ({:?}) - {}", members.len(), destination, class, result.describe());
+        let results = batch.member_results(&result, members.len());
+        for ((id, stored), result) in members.iter().zip(&results) {
+            self.finish(*id, claim_token, stored, result).await;
+        }
+        class
+    }
+
+    fn outcome(&self, destination: &str) -> &OutcomePolicy {
+        self.destinations
+            .get(destination)
+            .and_then(|d| d.outcome.as_ref())
+            .unwrap_or(&self.config.outcome)
+    }
+
+    // apply the outcome of a delivery attempt to the queued request
+    async fn finish(
+        &self,
+        id: i64,
+        claim_token: &str,
+        stored: &StoredRequest,
+        result: &DeliveryResult,
+    ) -> OutcomeClass {
+        let destination = self.destination_name(stored);
+        let outcome = self.outcome(destination);
+        let class = outcome.classify(result);
+        let attempts = stored.attempts + 1;
+        if let Err(e) = record_delivery_attempt(
+            id,
+            attempts,
+            destination,
+            &format!("{:?}", class).to_lowercase(),
+            result,
+            self.config.attempt_retention_days,
+            &self.pool,
+        )
+        .await
+        {
+            warn!(target:"app::requests", "could not record delivery attempt of request {} - {}", id, e);
+        }
+        match outcome.action(class) {
+            OutcomeAction::Delete => {
+                if class != OutcomeClass::Success {
+                    warn!(target:"app::requests", "request {} failed ({:?}), removing from queue - {}", id, class, result.describe());
+                }
+                debug!(target:"app::requests", "deleting request {} - {} - {} bytes", id, stored.uri, stored.body.len());
+                if let Err(e) = delete_request_from_db(id, claim_token, &self.pool).await {
+                    warn!(target:"app::requests", "could not remove request {}, it is delivered again once its lease expires - {}", id, e);
+                }
+            }
+            OutcomeAction::Retry if !self.config.retry.exhausted(attempts) => {
+                // never retry before the destination said it is ready again
+                let delay = self.config.retry.delay_secs(attempts).max(
+                    result
+                        .retry_after
+                        .map_or(0, |r| r.as_secs().min(self.config.retry.max_delay_secs)),
+                );
+                info!(target:"app::requests", "request {} attempt {} failed ({:?}), retrying in {}s - {}", id, attempts, class, delay, result.describe());
+                if let Err(e) = schedule_retry(id, claim_token, result, delay, &self.pool).await {
+                    warn!(target:"app::requests", "could not schedule a retry of request {}, it is retried once its lease expires - {}", id, e);
+                }
+            }
+            OutcomeAction::Retry | OutcomeAction::DeadLetter => {
+                warn!(target:"app::requests", "request {} failed ({:?}) after {} attempts, moving to dead letters - {}", id, class, attempts, result.describe());
+                if let Err(e) = dead_letter_request(id, claim_token, result, &self.pool).await {
+                    warn!(target:"app::requests", "could not dead letter request {} - {}", id, e);
+                }
+            }
+        }
+        class
+    }
+}
@@ -0,0 +1,105 @@
+use crate::routing::destinations::{DestinationConfig, DEFAULT_DESTINATION};
+use log::info;
+use reqwest::redirect::Policy;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+
+// long-lived clients keyed by destination name, so connections are pooled and reused
+pub type DestinationClients = HashMap<String, DestinationClient>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HttpVersion {
+    Negotiate,      // HTTP/2 when the server offers it during the TLS handshake
+    Http1Only,      // never HTTP/2
+    PriorKnowledge, // HTTP/2 without negotiation, for h2c backends
+}
+
+// [http_client] table in config/Settings.toml, or [destinations.<name>.client] to replace it
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ClientConfig {
+    pub connect_timeout_ms: u64,
+    pub read_timeout_secs: u64, // longest silence while reading the response body
+    pub pool_max_idle_per_host: usize,
+    pub pool_idle_timeout_secs: u64,
+    pub tcp_keepalive_secs: u64,
+    pub http_version: HttpVersion,
+    pub max_redirects: usize, // 0 delivers 3xx responses to the outcome policy instead of following them
+}
+
+impl Default for ClientConfig {
+    fn default() -> Self {
+        ClientConfig {
+            connect_timeout_ms: 5000,
+            read_timeout_secs: 30,
+            pool_max_idle_per_host: 8,
+            pool_idle_timeout_secs: 90,
+            tcp_keepalive_secs: 60,
+            http_version: HttpVersion::Negotiate,
+            max_redirects: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DestinationClient {
+    pub client: reqwest::Client,
+    pub read_timeout: Duration,
+}
+
+impl DestinationClient {
+    // timeout_secs bounds the whole delivery, from connecting to the last byte of the response
+    pub fn new(config: &ClientConfig, timeout_secs: u64) -> reqwest::Result<DestinationClient> {
+        let builder = reqwest::Client::builder()
+            .timeout(Duration::from_secs(timeout_secs))
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .pool_max_idle_per_host(config.pool_max_idle_per_host)
+            .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+            .tcp_keepalive(Duration::from_secs(config.tcp_keepalive_secs))
+            .redirect(match config.max_redirects {
+                0 => Policy::none(),
+                max => Policy::limited(max),
+            });
+        let builder = match config.http_version {
+            HttpVersion::Negotiate => builder,
+            HttpVersion::Http1Only => builder.http1_only(),
+            HttpVersion::PriorKnowledge => builder.http2_prior_knowledge(),
+        };
+        Ok(DestinationClient {
+            client: builder.build()?,
+            read_timeout: Duration::from_secs(config.read_timeout_secs.max(1)),
+        })
+    }
+}
+
+// one client for every configured destination and the default one
+pub fn build_clients(
+    destinations: &HashMap<String, DestinationConfig>,
+    default_config: &ClientConfig,
+    default_timeout_secs: u64,
+) -> DestinationClients {
+    let mut destinations = destinations.clone();
+    destinations
+        .entry(DEFAULT_DESTINATION.to_string())
+        .or_default();
+    destinations
+        .iter()
+        .map(|(name, destination)| {
+            let config = destination.client.as_ref().unwrap_or(default_config);
+            let timeout_secs = destination.timeout_secs.unwrap_or(default_timeout_secs);
+            info!(
+                "destination {} - {:?}, {}s timeout, {}ms connect timeout, {}s read timeout",
+                name,
+                config.http_version,
+                timeout_secs,
+                config.connect_timeout_ms,
+                config.read_timeout_secs
+            );
+            let client = DestinationClient::new(config, timeout_secs)
+                .unwrap_or_else(|e| panic!("cannot build http client for {}: {}", name, e));
+            (name.clone(), client)
+        })
+        .collect()
+}
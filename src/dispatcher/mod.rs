@@ -0,0 +1,8 @@
+pub mod batch;
+pub mod circuit_breaker;
+pub mod dispatch;
+pub mod http_client;
+pub mod outcome;
+pub mod rate_limit;
+pub mod retry;
+pub mod throttle;
@@ -0,0 +1,323 @@
+use crate::entities::delivery_result::{DeliveryResult, TransportError};
+use serde::Deserialize;
+
+// how a delivery attempt is classified
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeClass {
+    Success,
+    Retryable,
+    Permanent,
+}
+
+// what happens to the queued request for each class
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutcomeAction {
+    Delete,
+    Retry, // dead letters the request once max_attempts is reached
+    DeadLetter,
+}
+
+// a status code "204", a class "5xx" or an inclusive range "500-504"
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct StatusPattern {
+    low: u16,
+    high: u16,
+}
+
+impl TryFrom<String> for StatusPattern {
+    type Error = String;
+
+    fn try_from(pattern: String) -> Result<Self, Self::Error> {
+        let invalid = || format!("invalid status pattern {:?}", pattern);
+        let trimmed = pattern.trim().to_lowercase();
+        let (low, high) = if let Some(class) = trimmed.strip_suffix("xx") {
+            let class = class.parse::<u16>().map_err(|_| invalid())?;
+            (class * 100, class * 100 + 99)
+        } else if let Some((low, high)) = trimmed.split_once('-') {
+            (
+                low.trim().parse::<u16>().map_err(|_| invalid())?,
+                high.trim().parse::<u16>().map_err(|_| invalid())?,
+            )
+        } else {
+            let status = trimmed.parse::<u16>().map_err(|_| invalid())?;
+            (status, status)
+        };
+        if !(100..=599).contains(&low) || !(100..=599).contains(&high) || low > high {
+            return Err(invalid());
+        }
+        Ok(StatusPattern { low, high })
+    }
+}
+
+impl StatusPattern {
+    fn matches(&self, status: u16) -> bool {
+        (self.low..=self.high).contains(&status)
+    }
+
+    fn width(&self) -> u16 {
+        self.high - self.low
+    }
+}
+
+// reclassify a response whose body contains a marker, for APIs that report errors with a 200
+#[derive(Debug, Clone, Deserialize)]
+pub struct BodyMatch {
+    #[serde(default)]
+    pub status: Vec<StatusPattern>, // empty matches any status
+    pub contains: String,
+    pub class: OutcomeClass,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutcomeActions {
+    pub success: OutcomeAction,
+    pub retryable: OutcomeAction,
+    pub permanent: OutcomeAction,
+}
+
+impl Default for OutcomeActions {
+    fn default() -> Self {
+        OutcomeActions {
+            success: OutcomeAction::Delete,
+            retryable: OutcomeAction::Retry,
+            permanent: OutcomeAction::DeadLetter,
+        }
+    }
+}
+
+// [outcome] table in config/Settings.toml, or [destinations.<name>.outcome] to replace it for one destination
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct OutcomePolicy {
+    pub success: Vec<StatusPattern>,
+    pub retryable: Vec<StatusPattern>,
+    pub permanent: Vec<StatusPattern>,
+    pub unmatched: OutcomeClass, // statuses that match none of the lists
+    pub timeout: OutcomeClass,
+    pub connection_error: OutcomeClass,
+    pub transport_error: OutcomeClass, // any other failure to send or read the response
+    pub body_matches: Vec<BodyMatch>,
+    pub actions: OutcomeActions,
+}
+
+impl Default for OutcomePolicy {
+    fn default() -> Self {
+        let patterns = |p: &[&str]| {
+            p.iter()
+                .map(|p| StatusPattern::try_from(p.to_string()).unwrap())
+                .collect()
+        };
+        OutcomePolicy {
+            success: patterns(&["2xx"]),
+            retryable: patterns(&["408", "425", "429", "5xx"]),
+            permanent: patterns(&["4xx"]),
+            unmatched: OutcomeClass::Permanent,
+            timeout: OutcomeClass::Retryable,
+            connection_error: OutcomeClass::Retryable,
+            transport_error: OutcomeClass::Retryable,
+            body_matches: vec![],
+            actions: OutcomeActions::default(),
+        }
+    }
+}
+
+impl OutcomePolicy {
+    pub fn classify(&self, result: &DeliveryResult) -> OutcomeClass {
+        match (result.status, result.transport_error) {
+            (_, Some(TransportError::Timeout)) => self.timeout,
+            (_, Some(TransportError::Connect)) => self.connection_error,
+            (_, Some(TransportError::Other)) | (None, None) => self.transport_error,
+            (Some(status), None) => self
+                .match_body(status, result.response_body.as_deref())
+                .unwrap_or_else(|| self.match_status(status)),
+        }
+    }
+
+    pub fn action(&self, class: OutcomeClass) -> OutcomeAction {
+        match class {
+            OutcomeClass::Success => self.actions.success,
+            OutcomeClass::Retryable => self.actions.retryable,
+            OutcomeClass::Permanent => self.actions.permanent,
+        }
+    }
+
+    // first matching body rule wins
+    fn match_body(&self, status: u16, body: Option<&str>) -> Option<OutcomeClass> {
+        let body = body?;
+        self.body_matches
+            .iter()
+            .find(|m| {
+                (m.status.is_empty() || m.status.iter().any(|p| p.matches(status)))
+                    && body.contains(&m.contains)
+            })
+            .map(|m| m.class)
+    }
+
+    // the narrowest matching pattern wins, so "429" in retryable beats "4xx" in permanent
+    fn match_status(&self, status: u16) -> OutcomeClass {
+        [
+            (&self.success, OutcomeClass::Success),
+            (&self.retryable, OutcomeClass::Retryable),
+            (&self.permanent, OutcomeClass::Permanent),
+        ]
+        .iter()
+        .flat_map(|(patterns, class)| patterns.iter().map(move |p| (p, *class)))
+        .filter(|(p, _)| p.matches(status))
+        .min_by_key(|(p, _)| p.width())
+        .map(|(_, class)| class)
+        .unwrap_or(self.unmatched)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(status: u16, body: &str) -> DeliveryResult {
+        DeliveryResult {
+            status: Some(status),
+            response_body: Some(body.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn failure(kind: TransportError) -> DeliveryResult {
+        DeliveryResult {
+            error: Some("failed".to_string()),
+            transport_error: Some(kind),
+            ..Default::default()
+        }
+    }
+
+    fn pattern(p: &str) -> StatusPattern {
+        StatusPattern::try_from(p.to_string()).unwrap()
+    }
+
+    #[test]
+    fn parses_codes_classes_and_ranges() {
+        assert_eq!(
+            pattern("204"),
+            StatusPattern {
+                low: 204,
+                high: 204
+            }
+        );
+        assert_eq!(
+            pattern("5XX"),
+            StatusPattern {
+                low: 500,
+                high: 599
+            }
+        );
+        assert_eq!(
+            pattern(" 500 - 504 "),
+            StatusPattern {
+                low: 500,
+                high: 504
+            }
+        );
+        for invalid in ["", "abc", "6xx", "99", "504-500", "200-700", "4x"] {
+            assert!(
+                StatusPattern::try_from(invalid.to_string()).is_err(),
+                "{}",
+                invalid
+            );
+        }
+    }
+
+    #[test]
+    fn narrowest_pattern_wins() {
+        let policy = OutcomePolicy::default();
+        assert_eq!(policy.classify(&response(429, "")), OutcomeClass::Retryable);
+        assert_eq!(policy.classify(&response(404, "")), OutcomeClass::Permanent);
+        assert_eq!(policy.classify(&response(503, "")), OutcomeClass::Retryable);
+        assert_eq!(policy.classify(&response(204, "")), OutcomeClass::Success);
+
+        let policy = OutcomePolicy {
+            retryable: vec![pattern("500-599")],
+            permanent: vec![pattern("501")],
+            ..Default::default()
+        };
+        assert_eq!(policy.classify(&response(501, "")), OutcomeClass::Permanent);
+        assert_eq!(policy.classify(&response(502, "")), OutcomeClass::Retryable);
+    }
+
+    #[test]
+    fn equally_narrow_patterns_prefer_success_then_retryable() {
+        let policy = OutcomePolicy {
+            success: vec![pattern("409")],
+            retryable: vec![pattern("409"), pattern("410")],
+            permanent: vec![pattern("410")],
+            ..Default::default()
+        };
+        assert_eq!(policy.classify(&response(409, "")), OutcomeClass::Success);
+        assert_eq!(policy.classify(&response(410, "")), OutcomeClass::Retryable);
+    }
+
+    #[test]
+    fn unmatched_statuses_use_the_unmatched_class() {
+        let policy = OutcomePolicy::default();
+        assert_eq!(policy.classify(&response(302, "")), OutcomeClass::Permanent);
+        let policy = OutcomePolicy {
+            unmatched: OutcomeClass::Retryable,
+            ..Default::default()
+        };
+        assert_eq!(policy.classify(&response(101, "")), OutcomeClass::Retryable);
+    }
+
+    #[test]
+    fn transport_errors_have_their_own_classes() {
+        let policy = OutcomePolicy {
+            timeout: OutcomeClass::Permanent,
+            connection_error: OutcomeClass::Retryable,
+            transport_error: OutcomeClass::Success,
+            ..Default::default()
+        };
+        let timeout = policy.classify(&failure(TransportError::Timeout));
+        assert_eq!(timeout, OutcomeClass::Permanent);
+        let connect = policy.classify(&failure(TransportError::Connect));
+        assert_eq!(connect, OutcomeClass::Retryable);
+        let other = policy.classify(&failure(TransportError::Other));
+        assert_eq!(other, OutcomeClass::Success);
+        let no_response = policy.classify(&DeliveryResult::default());
+        assert_eq!(no_response, OutcomeClass::Success);
+    }
+
+    #[test]
+    fn body_matches_come_before_statuses_and_first_match_wins() {
+        let policy = OutcomePolicy {
+            body_matches: vec![
+                BodyMatch {
+                    status: vec![pattern("200")],
+                    contains: "\"ok\":false".to_string(),
+                    class: OutcomeClass::Retryable,
+                },
+                BodyMatch {
+                    status: vec![],
+                    contains: "false".to_string(),
+                    class: OutcomeClass::Permanent,
+                },
+            ],
+            ..Default::default()
+        };
+        let retry = policy.classify(&response(200, "{\"ok\":false}"));
+        assert_eq!(retry, OutcomeClass::Retryable);
+        let permanent = policy.classify(&response(201, "{\"ok\":false}"));
+        assert_eq!(permanent, OutcomeClass::Permanent);
+        let success = policy.classify(&response(200, "{\"ok\":true}"));
+        assert_eq!(success, OutcomeClass::Success);
+    }
+
+    #[test]
+    fn classes_map_to_configured_actions() {
+        let policy = OutcomePolicy::default();
+        assert_eq!(policy.action(OutcomeClass::Success), OutcomeAction::Delete);
+        assert_eq!(policy.action(OutcomeClass::Retryable), OutcomeAction::Retry);
+        let dead_letter = policy.action(OutcomeClass::Permanent);
+        assert_eq!(dead_letter, OutcomeAction::DeadLetter);
+    }
+}
@@ -0,0 +1,170 @@
+use crate::dispatcher::throttle::{AdaptiveConfig, AdaptiveThrottle};
+use crate::entities::delivery_result::DeliveryResult;
+use log::warn;
+use rocket::tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use rocket::tokio::time::{sleep, Duration, Instant};
+use std::sync::{Arc, Mutex};
+
+// longest Retry-After that is honored, so a bad header cannot stall a destination for days
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(3600);
+
+struct BucketState {
+    tokens: f64,
+    last: Instant, // last refill
+    rate: f64,
+    paused_until: Option<Instant>,
+}
+
+impl BucketState {
+    fn refill(&mut self, burst: f64, now: Instant) {
+        if self.rate > 0.0 {
+            self.tokens =
+                (self.tokens + now.duration_since(self.last).as_secs_f64() * self.rate).min(burst);
+        }
+        self.last = now;
+    }
+}
+
+// token bucket refilled at `rate` tokens per second, holding at most `burst` tokens
+pub struct TokenBucket {
+    burst: f64,
+    state: Mutex<BucketState>,
+}
+
+impl TokenBucket {
+    pub fn new(rate: f64, burst: u32) -> TokenBucket {
+        let burst = f64::from(burst.max(1));
+        TokenBucket {
+            burst,
+            state: Mutex::new(BucketState {
+                tokens: burst,
+                last: Instant::now(),
+                rate,
+                paused_until: None,
+            }),
+        }
+    }
+
+    // wait until a token is available and take it, a rate of 0 is unlimited
+    pub async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().unwrap();
+                let now = Instant::now();
+                match state.paused_until {
+                    Some(until) if until > now => until - now,
+                    _ if state.rate <= 0.0 => return,
+                    _ => {
+                        state.refill(self.burst, now);
+                        if state.tokens >= 1.0 {
+                            state.tokens -= 1.0;
+                            return;
+                        }
+                        Duration::from_secs_f64((1.0 - state.tokens) / state.rate)
+                    }
+                }
+            };
+            sleep(wait).await;
+        }
+    }
+
+    pub fn rate(&self) -> f64 {
+        self.state.lock().unwrap().rate
+    }
+
+    // tokens earned at the old rate are kept
+    pub fn set_rate(&self, rate: f64) {
+        let mut state = self.state.lock().unwrap();
+        state.refill(self.burst, Instant::now());
+        state.rate = rate;
+    }
+
+    // send nothing until the pause is over, and do not save up tokens while paused
+    pub fn pause(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        let until = Instant::now() + duration;
+        if state.paused_until.is_none_or(|paused| paused < until) {
+            state.paused_until = Some(until);
+            state.tokens = 0.0;
+            state.last = until;
+        }
+    }
+
+    // whether a token could be taken right now, without taking it
+    pub fn ready(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match state.paused_until {
+            Some(until) if until > now => false,
+            _ if state.rate <= 0.0 => true,
+            _ => {
+                state.refill(self.burst, now);
+                state.tokens >= 1.0
+            }
+        }
+    }
+}
+
+// pacing and concurrency for one destination
+pub struct DestinationLimiter {
+    name: String,
+    bucket: TokenBucket,
+    concurrency: Arc<Semaphore>,
+    adaptive: Option<AdaptiveThrottle>,
+}
+
+impl DestinationLimiter {
+    pub fn new(
+        name: &str,
+        rate: f64,
+        burst: u32,
+        max_concurrency: usize,
+        adaptive: Option<AdaptiveConfig>,
+    ) -> DestinationLimiter {
+        let adaptive = adaptive.map(AdaptiveThrottle::new);
+        let rate = match &adaptive {
+            Some(adaptive) => adaptive.clamp(rate),
+            None => rate,
+        };
+        DestinationLimiter {
+            name: name.to_string(),
+            bucket: TokenBucket::new(rate, burst),
+            concurrency: Arc::new(Semaphore::new(max_concurrency.max(1))),
+            adaptive,
+        }
+    }
+
+    // hold the returned permit for the duration of the delivery
+    pub async fn acquire(&self) -> OwnedSemaphorePermit {
+        let permit = self
+            .concurrency
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("concurrency semaphore is never closed");
+        self.bucket.acquire().await;
+        permit
+    }
+
+    // a free in-flight slot and a token are available, workers leave the requests of a destination
+    // that is not ready in the queue, including while it is paused by a Retry-After
+    pub fn ready(&self) -> bool {
+        self.concurrency.available_permits() > 0 && self.bucket.ready()
+    }
+
+    // adjust pacing to how the destination handled a delivery
+    pub fn observe(&self, result: &DeliveryResult, failed: bool) {
+        if let Some(retry_after) = result.retry_after {
+            let retry_after = retry_after.min(MAX_RETRY_AFTER);
+            warn!(
+                "destination {} asked to retry after {}s, pausing delivery",
+                self.name,
+                retry_after.as_secs()
+            );
+            self.bucket.pause(retry_after);
+        }
+        if let Some(adaptive) = &self.adaptive {
+            adaptive.observe(&self.name, &self.bucket, result, failed);
+        }
+    }
+}
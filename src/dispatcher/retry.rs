@@ -0,0 +1,43 @@
+use rand::Rng;
+use std::collections::HashMap;
+
+// retry settings from the top level of config/Settings.toml
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub max_attempts: i32,
+    pub base_delay_secs: u64,
+    pub max_delay_secs: u64,
+}
+
+impl RetryPolicy {
+    pub fn from_settings(settings_map: &HashMap<String, String>) -> RetryPolicy {
+        let get = |key: &str, default: u64| {
+            settings_map
+                .get(key)
+                .map(|v| {
+                    v.parse::<u64>()
+                        .unwrap_or_else(|_| panic!("cannot parse {}", key))
+                })
+                .unwrap_or(default)
+        };
+        RetryPolicy {
+            max_attempts: get("max_attempts", 5).max(1) as i32,
+            base_delay_secs: get("retry_base_delay_secs", 5).max(1),
+            max_delay_secs: get("retry_max_delay_secs", 3600).max(1),
+        }
+    }
+
+    pub fn exhausted(&self, attempts: i32) -> bool {
+        attempts >= self.max_attempts
+    }
+
+    // exponential backoff with equal jitter, half the delay is fixed and half is random
+    pub fn delay_secs(&self, attempts: i32) -> u64 {
+        let exponent = attempts.saturating_sub(1).clamp(0, 30) as u32;
+        let delay = self
+            .base_delay_secs
+            .saturating_mul(2u64.saturating_pow(exponent))
+            .min(self.max_delay_secs);
+        delay / 2 + rand::thread_rng().gen_range(0..=delay - delay / 2)
+    }
+}
@@ -0,0 +1,86 @@
+use crate::dispatcher::rate_limit::TokenBucket;
+use crate::entities::delivery_result::DeliveryResult;
+use log::info;
+use rocket::tokio::time::{Duration, Instant};
+use serde::Deserialize;
+use std::sync::Mutex;
+
+// [destinations.<name>.adaptive] table in config/Settings.toml, the destination rate is the starting point
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AdaptiveConfig {
+    pub min_rate: f64,          // requests per second the rate never drops below
+    pub max_rate: f64,          // requests per second the rate never climbs above
+    pub target_latency_ms: u64, // slower responses count as a sign of overload
+    pub increase: f64,          // requests per second added after each healthy delivery
+    pub decrease_factor: f64,   // rate multiplier after an overloaded delivery
+    pub cooldown_ms: u64,       // minimum time between two decreases
+}
+
+impl Default for AdaptiveConfig {
+    fn default() -> Self {
+        AdaptiveConfig {
+            min_rate: 0.1,
+            max_rate: 10.0,
+            target_latency_ms: 1000,
+            increase: 0.1,
+            decrease_factor: 0.5,
+            cooldown_ms: 1000,
+        }
+    }
+}
+
+// additive increase, multiplicative decrease of a destination's rate
+pub struct AdaptiveThrottle {
+    config: AdaptiveConfig,
+    last_decrease: Mutex<Option<Instant>>,
+}
+
+impl AdaptiveThrottle {
+    pub fn new(config: AdaptiveConfig) -> AdaptiveThrottle {
+        AdaptiveThrottle {
+            config,
+            last_decrease: Mutex::new(None),
+        }
+    }
+
+    pub fn clamp(&self, rate: f64) -> f64 {
+        rate.clamp(
+            self.config.min_rate,
+            self.config.max_rate.max(self.config.min_rate),
+        )
+    }
+
+    // retryable failures, including 429 and 503, and slow responses halve the rate,
+    // deliveries that were fast and healthy raise it a little
+    pub fn observe(&self, name: &str, bucket: &TokenBucket, result: &DeliveryResult, failed: bool) {
+        let slow = result
+            .latency
+            .is_some_and(|l| l > Duration::from_millis(self.config.target_latency_ms));
+        let rate = bucket.rate();
+        if failed || slow {
+            // responses to requests sent before the last decrease say nothing new
+            let mut last_decrease = self.last_decrease.lock().unwrap();
+            let cooldown = Duration::from_millis(self.config.cooldown_ms);
+            if last_decrease.is_some_and(|last| last.elapsed() < cooldown) {
+                return;
+            }
+            *last_decrease = Some(Instant::now());
+            let slower = self.clamp(rate * self.config.decrease_factor);
+            if slower < rate {
+                info!(
+                    "destination {} is {}, slowing to {:.2} requests per second",
+                    name,
+                    if failed { "failing" } else { "slow" },
+                    slower
+                );
+                bucket.set_rate(slower);
+            }
+        } else {
+            let faster = self.clamp(rate + self.config.increase);
+            if faster > rate {
+                bucket.set_rate(faster);
+            }
+        }
+    }
+}
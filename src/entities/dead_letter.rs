@@ -0,0 +1,88 @@
+use crate::entities::storedrequest::StoredRequest;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+// the columns read by summary_from_row, lists leave bodies and failure texts in the table
+pub const SUMMARY_COLUMNS: &str =
+    "id, request_id, parent_id, method, uri, destination, priority, attempts, last_status, dead_lettered_at";
+
+// a request that exhausted its retries, kept with its failure history for inspection and redrive
+#[derive(Debug, Clone)]
+pub struct DeadLetter {
+    pub id: i64,
+    pub request_id: i64,
+    pub request: StoredRequest,
+    pub failure_history: Value,
+    pub last_status: Option<i32>,
+    pub last_response: Option<String>,
+    pub dead_lettered_at: DateTime<Utc>,
+}
+
+impl DeadLetter {
+    pub fn from_row(row: &MySqlRow) -> DeadLetter {
+        DeadLetter {
+            id: row.get("id"),
+            request_id: row.get("request_id"),
+            request: StoredRequest::from_row(row),
+            failure_history: row
+                .get::<Option<String>, _>("failure_history")
+                .and_then(|h| serde_json::from_str(&h).ok())
+                .unwrap_or_else(|| json!([])),
+            last_status: row.get("last_status"),
+            last_response: row.get("last_response"),
+            dead_lettered_at: row.get("dead_lettered_at"),
+        }
+    }
+
+    // the summary of a row holding only SUMMARY_COLUMNS
+    pub fn summary_from_row(row: &MySqlRow) -> Value {
+        json!({
+            "id": row.get::<i64, _>("id"),
+            "request_id": row.get::<i64, _>("request_id"),
+            "parent_id": row.get::<Option<i64>, _>("parent_id"),
+            "method": row.get::<String, _>("method"),
+            "uri": row.get::<String, _>("uri"),
+            "destination": row.get::<Option<String>, _>("destination"),
+            "priority": row.get::<i32, _>("priority"),
+            "attempts": row.get::<i32, _>("attempts"),
+            "last_status": row.get::<Option<i32>, _>("last_status"),
+            "dead_lettered_at": row.get::<DateTime<Utc>, _>("dead_lettered_at").to_rfc3339(),
+        })
+    }
+
+    pub fn summary(&self) -> Value {
+        json!({
+            "id": self.id,
+            "request_id": self.request_id,
+            "parent_id": self.request.parent_id,
+            "method": self.request.method,
+            "uri": self.request.uri,
+            "destination": self.request.destination,
+            "priority": self.request.priority,
+            "attempts": self.request.attempts,
+            "last_status": self.last_status,
+            "dead_lettered_at": self.dead_lettered_at.to_rfc3339(),
+        })
+    }
+
+    // everything needed to understand the failure, bodies that are not UTF-8 are base64 encoded
+    pub fn detail(&self) -> Value {
+        let mut detail = self.summary();
+        detail["host"] = json!(self.request.host);
+        detail["route"] = json!(self.request.route);
+        detail["headers"] = json!(self.request.headers);
+        detail["content_type"] = json!(self.request.content_type);
+        detail["content_encoding"] = json!(self.request.content_encoding);
+        match std::str::from_utf8(&self.request.body) {
+            Ok(text) => detail["body"] = json!(text),
+            Err(_) => detail["body_base64"] = json!(STANDARD.encode(&self.request.body)),
+        }
+        detail["failure_history"] = self.failure_history.clone();
+        detail["last_response"] = json!(self.last_response);
+        detail
+    }
+}
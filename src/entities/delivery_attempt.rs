@@ -0,0 +1,60 @@
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
+
+// one recorded delivery attempt, kept for debugging rejections after the fact
+#[derive(Debug, Clone)]
+pub struct DeliveryAttempt {
+    pub id: i64,
+    pub request_id: i64,
+    pub attempt: i32, // 1 for the first delivery of the request
+    pub destination: String,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+    pub latency_ms: Option<i64>, // until the response headers arrived
+    pub status: Option<i32>,
+    pub outcome: String,            // success, retryable or permanent
+    pub error_kind: Option<String>, // timeout, connect or other when no response was received
+    pub error: Option<String>,
+    pub response_headers: Option<String>,
+    pub response_body: Option<String>,
+}
+
+impl DeliveryAttempt {
+    pub fn from_row(row: &MySqlRow) -> DeliveryAttempt {
+        DeliveryAttempt {
+            id: row.get("id"),
+            request_id: row.get("request_id"),
+            attempt: row.get("attempt"),
+            destination: row.get("destination"),
+            started_at: row.get("started_at"),
+            finished_at: row.get("finished_at"),
+            latency_ms: row.get("latency_ms"),
+            status: row.get("status"),
+            outcome: row.get("outcome"),
+            error_kind: row.get("error_kind"),
+            error: row.get("error"),
+            response_headers: row.get("response_headers"),
+            response_body: row.get("response_body"),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "id": self.id,
+            "request_id": self.request_id,
+            "attempt": self.attempt,
+            "destination": self.destination,
+            "started_at": self.started_at.map(|t| t.to_rfc3339()),
+            "finished_at": self.finished_at.map(|t| t.to_rfc3339()),
+            "latency_ms": self.latency_ms,
+            "status": self.status,
+            "outcome": self.outcome,
+            "error_kind": self.error_kind,
+            "error": self.error,
+            "response_headers": self.response_headers,
+            "response_body": self.response_body,
+        })
+    }
+}
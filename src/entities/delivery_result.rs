@@ -0,0 +1,47 @@
+use chrono::{DateTime, Utc};
+use std::time::Duration;
+
+// what came back from one delivery attempt
+#[derive(Debug, Clone, Default)]
+pub struct DeliveryResult {
+    pub status: Option<u16>,   // none when no response was received
+    pub error: Option<String>, // transport error, when the request could not be sent
+    pub transport_error: Option<TransportError>,
+    pub response_body: Option<String>, // truncated to RESPONSE_BODY_LIMIT bytes
+    pub response_headers: Option<String>, // "name: value" lines, truncated to RESPONSE_BODY_LIMIT bytes
+    pub latency: Option<Duration>,        // until the response headers arrived
+    pub retry_after: Option<Duration>,    // from the Retry-After header of a 429 or 503
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>, // once the response was read or the attempt failed
+}
+
+// kind of failure when no response was received
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransportError {
+    Timeout,
+    Connect,
+    Other,
+}
+
+pub const RESPONSE_BODY_LIMIT: usize = 4096;
+
+impl TransportError {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            TransportError::Timeout => "timeout",
+            TransportError::Connect => "connect",
+            TransportError::Other => "other",
+        }
+    }
+}
+
+impl DeliveryResult {
+    // short description stored as last_error
+    pub fn describe(&self) -> String {
+        match (&self.status, &self.error) {
+            (_, Some(error)) => error.clone(),
+            (Some(status), None) => format!("destination responded with {}", status),
+            (None, None) => "no response".to_string(),
+        }
+    }
+}
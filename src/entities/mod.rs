@@ -1 +1,4 @@
+pub mod dead_letter;
+pub mod delivery_attempt;
+pub mod delivery_result;
 pub mod storedrequest;
@@ -1,11 +1,46 @@
-use rocket::http::HeaderMap;
+use crate::manage_requests::headers::{deserialize_headers, StoredHeaders};
+use chrono::{DateTime, Utc};
+use sqlx::mysql::MySqlRow;
+use sqlx::Row;
 
 #[derive(Debug, Clone)]
-pub struct StoredRequest<'a> {
+pub struct StoredRequest {
     pub method: String,
     pub host: String,
     pub port: u16,
     pub uri: String,
-    pub headers: HeaderMap<'a>,
-    pub body: String,
+    pub headers: StoredHeaders,
+    pub body: Vec<u8>,
+    pub content_type: Option<String>,
+    pub content_encoding: Option<String>,
+    pub route: Option<String>, // name of the matching [routes.<name>] table, if any
+    pub destination: Option<String>, // name of a [destinations.<name>] table, the default destination when none
+    pub attempts: i32,               // failed deliveries so far
+    pub priority: i32,               // higher is delivered first
+    pub deliver_at: Option<DateTime<Utc>>, // not sent before this time
+    pub parent_id: Option<i64>,      // fan-out request holding the body, for one of its deliveries
+    pub partition_key: Option<String>, // requests sharing a key are delivered in order, one at a time
+}
+
+impl StoredRequest {
+    // rebuild a queued request from a row of the requests table
+    pub fn from_row(row: &MySqlRow) -> StoredRequest {
+        StoredRequest {
+            method: row.get("method"),
+            host: row.get("host"),
+            port: row.get::<String, _>("port").parse().unwrap_or(80),
+            uri: row.get("uri"),
+            headers: deserialize_headers(row.get("headers")),
+            body: row.get("body"),
+            content_type: row.get("content_type"),
+            content_encoding: row.get("content_encoding"),
+            route: row.get("route"),
+            destination: row.get("destination"),
+            attempts: row.get("attempts"),
+            priority: row.get("priority"),
+            deliver_at: row.get("deliver_at"),
+            parent_id: row.get("parent_id"),
+            partition_key: row.get("partition_key"),
+        }
+    }
 }
@@ -1,7 +1,10 @@
 use rocket::http::Status;
 use rocket::outcome::Outcome;
 use rocket::request::{FromRequest, Request};
+use std::collections::HashMap;
+use std::fmt;
 
+// guards the /reque/ admin endpoints, the x-api-key header must match api_key in Settings.toml
 pub struct ApiKey<'r>(&'r str);
 
 #[derive(Debug)]
@@ -17,18 +20,32 @@ impl<'r> FromRequest<'r> for ApiKey<'r> {
     async fn from_request(
         req: &'r Request<'_>,
     ) -> Outcome<ApiKey<'r>, (Status, ApiKeyError), Status> {
-        let _e = ApiKeyError::InvalidError;
-        let _e2 = ApiKeyError::MissingError;
+        let expected = req
+            .rocket()
+            .state::<HashMap<String, String>>()
+            .and_then(|settings| settings.get("api_key"))
+            .filter(|key| !key.is_empty());
 
-        match req.headers().get_one("x-api-key") {
-            Some(key) => Outcome::Success(ApiKey(key)),
-            None => Outcome::Success(ApiKey("")),
+        match (req.headers().get_one("x-api-key"), expected) {
+            (None, _) => Outcome::Error((Status::Unauthorized, ApiKeyError::MissingError)),
+            (Some(key), Some(expected)) if constant_time_eq(key, expected) => {
+                Outcome::Success(ApiKey(key))
+            }
+            (Some(_), _) => Outcome::Error((Status::Unauthorized, ApiKeyError::InvalidError)),
         }
     }
 }
 
-impl<'r> ToString for ApiKey<'r> {
-    fn to_string(&self) -> String {
-        String::from_utf8(Vec::from(self.0.as_bytes())).unwrap()
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |diff, (x, y)| diff | (x ^ y))
+            == 0
+}
+
+impl<'r> fmt::Display for ApiKey<'r> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
     }
 }
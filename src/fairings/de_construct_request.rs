@@ -6,10 +6,8 @@ use rocket::request::{FromRequest, Request};
 pub struct RRequest<'a> {
     pub method: String,
     pub host: String,
-    pub port: u16,
     pub uri: String,
     pub headers: HeaderMap<'a>,
-    pub body: String,
 }
 
 #[derive(Debug)]
@@ -30,11 +28,9 @@ impl<'r> FromRequest<'r> for RRequest<'r> {
 
         let rr = Outcome::Success(RRequest {
             method: req.method().to_string(),
-            host: req.host().unwrap().to_string(),
-            port: 0,
+            host: req.host().map(|h| h.to_string()).unwrap_or_default(),
             uri: req.uri().to_string(),
             headers: req.headers().clone(),
-            body: "".to_string(),
         });
         rr
     }
@@ -1,30 +1,51 @@
 #[macro_use]
 extern crate rocket;
-use chrono::Local;
+use chrono::{DateTime, Local, Utc};
 use std::collections::HashMap;
 use std::net::SocketAddr;
+use std::sync::Arc;
 
+mod admin;
+mod dispatcher;
 mod entities;
 mod fairings;
 mod manage_requests;
-
+mod plugins;
+mod routing;
+
+use crate::dispatcher::circuit_breaker::CircuitBreakerConfig;
+use crate::dispatcher::dispatch::{Dispatcher, DispatcherConfig};
+use crate::dispatcher::http_client::{build_clients, ClientConfig};
+use crate::dispatcher::outcome::OutcomePolicy;
+use crate::entities::dead_letter::DeadLetter;
 use crate::entities::storedrequest::StoredRequest;
 use crate::fairings::de_construct_request::RRequest;
-use crate::manage_requests::request_funcs::{
-    delete_request_from_db, send_stored_request, write_request_to_db,
+use crate::manage_requests::dead_letter_funcs::{
+    find_dead_letter_by_request, find_dead_letters_by_parent,
 };
+use crate::manage_requests::headers::{filter_headers, HeaderPolicy};
+use crate::manage_requests::request_funcs::{write_idempotent_request_to_db, write_request_to_db};
+use crate::plugins::{load_plugins, PluginHandler};
+use crate::routing::body_limits::BodyLimits;
+use crate::routing::destinations::DestinationConfig;
+use crate::routing::priority::{clamp_priority, PriorityRule};
+use crate::routing::route_table::{RouteConfig, RouteTable};
+use crate::routing::schedule::latest_deliver_at;
 use config::Config;
 use log::info;
 use log::LevelFilter;
 use log4rs::append::console::ConsoleAppender;
 use log4rs::config::{Appender, Root};
 use log4rs::Config as LogConfig;
+use rocket::custom;
+use rocket::data::{Data, ToByteUnit};
 use rocket::fairing::{Fairing, Info, Kind};
+use rocket::http::uri::{fmt::Path, Segments};
 use rocket::http::{Header, Status};
 use rocket::request::Request;
-use rocket::tokio::time::{interval_at, Instant};
+use rocket::serde::json::Json;
 use rocket::Response;
-use rocket::{custom, tokio};
+use sqlx::mysql::MySqlRow;
 use sqlx::{MySqlPool, Row};
 
 // // // // // // // // // // // // // // // // // // // // // // // //
@@ -36,70 +57,447 @@ async fn index<'a>(
     request: RRequest<'a>,
     socket_addr: SocketAddr,
     pool: &rocket::State<MySqlPool>,
-) -> Result<(), ErrorResponder> {
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+) -> Result<Accepted, ErrorResponder> {
     let _is_pool_closed = pool.is_closed();
     info!(target:"app::requests", "ROOT PATH - From: {}", socket_addr.ip().to_string());
     let now = Local::now().timestamp().to_string();
-    let new_req = StoredRequest {
-        // src/entities/storedrequest.rs
-        method: request.method,
-        host: request.host,
-        port: 80,
-        uri: request.uri,
-        headers: request.headers,
-        body: now,
-    };
 
-    let _ = write_request_to_db(new_req.clone(), pool).await; // create stored request and insert into database, ignoring the result
+    queue_request(
+        request,
+        now.into_bytes(),
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
+}
 
-    Ok(())
+#[post("/delay/<delay_num>", data = "<data>")]
+async fn slow_test_server<'a>(
+    // for testing purposes, https://github.com/helloimalemur/Slow-Server to simulate slow-responding server
+    delay_num: i64,
+    request: RRequest<'a>,
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    data: Data<'a>,
+) -> Result<Accepted, ErrorResponder> {
+    // queued verbatim, the slow server delays its response by delay_num seconds on delivery,
+    // use X-Reque-Delay to hold the request in the queue instead
+    info!(target:"app::requests", "slow server test request - {} second response delay", delay_num);
+
+    let data = read_body(&request, data, route_table).await?;
+    queue_request(
+        request,
+        data,
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
 }
 
-#[post("/plugins/shopify", data = "<data>")]
-async fn shopify_webhook<'a>(
+// catch-all ingestion routes, ranked below the routes above so they remain special cases;
+// the raw segments are taken as they are so dot-files such as /.well-known/... are queued too
+#[get("/<path..>", rank = 20)]
+async fn catch_all_get<'a>(
+    path: Segments<'a, Path>,
     request: RRequest<'a>,
-    pool: &rocket::State<MySqlPool>, // see line 237, wrapping this in a State<> signals Rocket to bring this into scope
-    data: String,
-) -> Result<(), ErrorResponder> {
-    println!("{:?}", request);
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+) -> Result<Accepted, ErrorResponder> {
+    reserved_path(&path)?;
+    queue_request(
+        request,
+        Vec::new(),
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
+}
 
-    let new_req = StoredRequest {
-        // src/entities/storedrequest.rs
-        method: request.method,
-        host: request.host,
-        port: 80,
-        uri: request.uri,
-        headers: request.headers,
-        body: data,
-    };
+#[post("/<path..>", data = "<data>", rank = 20)]
+async fn catch_all_post<'a>(
+    path: Segments<'a, Path>,
+    request: RRequest<'a>,
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    data: Data<'a>,
+) -> Result<Accepted, ErrorResponder> {
+    reserved_path(&path)?;
+    let data = read_body(&request, data, route_table).await?;
+    queue_request(
+        request,
+        data,
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
+}
 
-    let _ = write_request_to_db(new_req.clone(), pool).await; // create stored request and insert into database, ignoring the result
+#[put("/<path..>", data = "<data>", rank = 20)]
+async fn catch_all_put<'a>(
+    path: Segments<'a, Path>,
+    request: RRequest<'a>,
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    data: Data<'a>,
+) -> Result<Accepted, ErrorResponder> {
+    reserved_path(&path)?;
+    let data = read_body(&request, data, route_table).await?;
+    queue_request(
+        request,
+        data,
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
+}
 
-    Ok(())
+#[patch("/<path..>", data = "<data>", rank = 20)]
+async fn catch_all_patch<'a>(
+    path: Segments<'a, Path>,
+    request: RRequest<'a>,
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    data: Data<'a>,
+) -> Result<Accepted, ErrorResponder> {
+    reserved_path(&path)?;
+    let data = read_body(&request, data, route_table).await?;
+    queue_request(
+        request,
+        data,
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
 }
 
-#[post("/delay/<delay_num>", data = "<data>")]
-async fn slow_test_server<'a>(
-    // for testing purposes, https://github.com/helloimalemur/Slow-Server to simulate slow-responding server
-    delay_num: i64,
+#[delete("/<path..>", data = "<data>", rank = 20)]
+async fn catch_all_delete<'a>(
+    path: Segments<'a, Path>,
     request: RRequest<'a>,
     pool: &rocket::State<MySqlPool>,
-    data: String,
-) -> Result<(), ErrorResponder> {
-    println!("{:?}\n--- delay: {}", request, delay_num);
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    data: Data<'a>,
+) -> Result<Accepted, ErrorResponder> {
+    reserved_path(&path)?;
+    let data = read_body(&request, data, route_table).await?;
+    queue_request(
+        request,
+        data,
+        pool,
+        header_policy,
+        route_table,
+        settings_map,
+        None,
+    )
+    .await
+}
+
+// plugin paths only accept requests through their own verified routes, /reque/ is reserved for reQue itself
+fn reserved_path(path: &Segments<'_, Path>) -> Result<(), ErrorResponder> {
+    if matches!(path.get(0), Some("plugins" | "reque")) {
+        return Err(ErrorResponder::with_status(Status::NotFound, "not found"));
+    }
+    Ok(())
+}
+
+// read the request body up to the limit for its route and content type
+async fn read_body(
+    request: &RRequest<'_>,
+    data: Data<'_>,
+    route_table: &RouteTable,
+) -> Result<Vec<u8>, ErrorResponder> {
+    let route = route_table.resolve(request);
+    let limit = route_table.body_limit(route.as_deref(), request.headers.get_one("content-type"));
+    let body = data
+        .open(limit.bytes())
+        .into_bytes()
+        .await
+        .map_err(anyhow::Error::from)?;
+    if !body.is_complete() {
+        return Err(ErrorResponder::with_status(
+            Status::PayloadTooLarge,
+            &format!("payload exceeds the {} byte limit", limit),
+        ));
+    }
+    Ok(body.into_inner())
+}
+
+// build a StoredRequest from the incoming request and queue it verbatim,
+// repeats of an idempotency key inside the dedup window return the original request
+#[allow(clippy::too_many_arguments)]
+async fn queue_request(
+    request: RRequest<'_>,
+    data: Vec<u8>,
+    pool: &rocket::State<MySqlPool>,
+    header_policy: &rocket::State<HeaderPolicy>,
+    route_table: &rocket::State<RouteTable>,
+    settings_map: &rocket::State<HashMap<String, String>>,
+    dedup_key: Option<String>, // provider assigned key from a webhook plugin
+) -> Result<Accepted, ErrorResponder> {
+    info!(target:"app::requests", "{} {} - queued", request.method, request.uri);
+
+    let route = route_table.resolve(&request);
+    let route_config = route.as_deref().and_then(|name| route_table.get(name));
+    let idempotency_key = request
+        .headers
+        .get_one("idempotency-key")
+        .or_else(|| {
+            route_config
+                .and_then(|r| r.idempotency_header.as_deref())
+                .and_then(|header| request.headers.get_one(header))
+        })
+        .map(String::from)
+        .or(dedup_key)
+        // keys are scoped to the ingress path
+        .map(|key| {
+            format!(
+                "{} {}",
+                request.uri.split('?').next().unwrap_or_default(),
+                key
+            )
+        });
+    let idempotency_window = route_config
+        .and_then(|r| r.idempotency_window_secs)
+        .or_else(|| {
+            settings_map
+                .get("idempotency_window_secs")
+                .and_then(|w| w.parse().ok())
+        })
+        .unwrap_or(86400);
+    let priority = match request.headers.get_one("x-reque-priority") {
+        Some(value) => clamp_priority(value.trim().parse::<i32>().map_err(|_| {
+            ErrorResponder::with_status(Status::BadRequest, "X-Reque-Priority must be an integer")
+        })?),
+        None => route_table.priority(route.as_deref(), &request.headers),
+    };
+    let deliver_at = deliver_at(&request, route_config)?;
+    let fanout = route_config
+        .map(|r| r.delivery_destinations())
+        .unwrap_or_default();
+    let partition_key = route
+        .as_deref()
+        .zip(route_config)
+        .and_then(|(name, r)| r.partition_key(name, &request.headers, &data));
 
     let new_req = StoredRequest {
+        // src/entities/storedrequest.rs
         method: request.method,
         host: request.host,
         port: 80,
+        // with several destinations each delivery row names its own
+        destination: match fanout.as_slice() {
+            [destination] => Some(destination.clone()),
+            _ => None,
+        },
+        route,
         uri: request.uri,
-        headers: request.headers,
+        content_type: request.headers.get_one("content-type").map(String::from),
+        content_encoding: request
+            .headers
+            .get_one("content-encoding")
+            .map(String::from),
+        headers: filter_headers(&request.headers, header_policy),
         body: data,
+        attempts: 0,
+        priority,
+        deliver_at,
+        parent_id: None,
+        partition_key,
     };
 
-    let _ = write_request_to_db(new_req.clone(), pool).await;
+    // create stored request and insert into database
+    match idempotency_key {
+        Some(key) => {
+            let (id, duplicate) =
+                write_idempotent_request_to_db(new_req, &fanout, &key, idempotency_window, pool)
+                    .await?;
+            if duplicate {
+                info!(target:"app::requests", "duplicate of request {} - not queued", id);
+            }
+            Ok(Accepted::new(id, duplicate))
+        }
+        None => Ok(Accepted::new(
+            write_request_to_db(new_req, &fanout, pool).await?,
+            false,
+        )),
+    }
+}
 
-    Ok(())
+// earliest delivery time from X-Reque-Deliver-At (RFC 3339), X-Reque-Delay (seconds)
+// or the route schedule, in that order
+fn deliver_at(
+    request: &RRequest<'_>,
+    route_config: Option<&RouteConfig>,
+) -> Result<Option<DateTime<Utc>>, ErrorResponder> {
+    let now = Utc::now();
+    if let Some(value) = request.headers.get_one("x-reque-deliver-at") {
+        let deliver_at = DateTime::parse_from_rfc3339(value.trim()).map_err(|_| {
+            ErrorResponder::with_status(
+                Status::BadRequest,
+                "X-Reque-Deliver-At must be an RFC 3339 time",
+            )
+        })?;
+        return storable_deliver_at(deliver_at.with_timezone(&Utc), "X-Reque-Deliver-At")
+            .map(|t| Some(t).filter(|t| *t > now));
+    }
+    if let Some(value) = request.headers.get_one("x-reque-delay") {
+        let delay = value.trim().parse::<u32>().map_err(|_| {
+            ErrorResponder::with_status(
+                Status::BadRequest,
+                "X-Reque-Delay must be a whole number of seconds",
+            )
+        })?;
+        return storable_deliver_at(
+            now + chrono::Duration::seconds(delay as i64),
+            "X-Reque-Delay",
+        )
+        .map(|t| Some(t).filter(|_| delay > 0));
+    }
+    Ok(route_config.and_then(|r| r.schedule.deliver_at(now)))
+}
+
+// a delivery time past the end of the deliver_at column is a bad request, not a database error
+fn storable_deliver_at(
+    deliver_at: DateTime<Utc>,
+    header: &str,
+) -> Result<DateTime<Utc>, ErrorResponder> {
+    let latest = latest_deliver_at();
+    if deliver_at > latest {
+        return Err(ErrorResponder::with_status(
+            Status::BadRequest,
+            &format!(
+                "{} is past the latest delivery time, {}",
+                header,
+                latest.to_rfc3339()
+            ),
+        ));
+    }
+    Ok(deliver_at)
+}
+
+// queued request status, linked from the Location header of every ingestion response,
+// fan-out requests list each of their deliveries; ids are sequential and the endpoint is open,
+// so it reports progress only, the request itself and error texts stay behind the admin endpoints
+#[get("/reque/requests/<id>")]
+async fn request_status(
+    id: i64,
+    pool: &rocket::State<MySqlPool>,
+) -> Result<Json<serde_json::Value>, ErrorResponder> {
+    const STATUS_COLUMNS: &str = "id, priority, deliver_at, attempts, next_attempt_at, fanout";
+    let row = sqlx::query(&format!(
+        "SELECT {} FROM requests WHERE id = ?",
+        STATUS_COLUMNS
+    ))
+    .bind(id)
+    .fetch_optional(&**pool)
+    .await
+    .map_err(anyhow::Error::from)?;
+
+    match row {
+        Some(row) if row.get::<bool, _>("fanout") => {
+            let queued = sqlx::query(&format!(
+                "SELECT {} FROM requests WHERE parent_id = ? ORDER BY id ASC",
+                STATUS_COLUMNS
+            ))
+            .bind(id)
+            .fetch_all(&**pool)
+            .await
+            .map_err(anyhow::Error::from)?;
+            let dead_lettered = find_dead_letters_by_parent(id, pool).await?;
+            let mut status = queued_status(&row);
+            status["status"] = serde_json::json!("fanned_out");
+            status["deliveries"] = queued
+                .iter()
+                .map(queued_status)
+                .chain(dead_lettered.iter().map(dead_letter_status))
+                .collect();
+            Ok(Json(status))
+        }
+        Some(row) => Ok(Json(queued_status(&row))),
+        None => match find_dead_letter_by_request(id, pool).await? {
+            Some(dead_letter) => Ok(Json(dead_letter_status(&dead_letter))),
+            None => {
+                // a finished fan-out request is only remembered through its dead lettered deliveries
+                let dead_lettered = find_dead_letters_by_parent(id, pool).await?;
+                if dead_lettered.is_empty() {
+                    return Err(ErrorResponder::with_status(
+                        Status::NotFound,
+                        "request is not queued, it was delivered or removed",
+                    ));
+                }
+                Ok(Json(serde_json::json!({
+                    "id": id,
+                    "status": "fanned_out",
+                    "deliveries": dead_lettered.iter().map(dead_letter_status).collect::<Vec<_>>(),
+                })))
+            }
+        },
+    }
+}
+
+fn queued_status(row: &MySqlRow) -> serde_json::Value {
+    serde_json::json!({
+        "id": row.get::<i64, _>("id"),
+        "status": "queued",
+        "priority": row.get::<i32, _>("priority"),
+        "deliver_at": row
+            .get::<Option<DateTime<Utc>>, _>("deliver_at")
+            .map(|t| t.to_rfc3339()),
+        "attempts": row.get::<i32, _>("attempts"),
+        "next_attempt_at": row
+            .get::<Option<DateTime<Utc>>, _>("next_attempt_at")
+            .map(|t| t.to_rfc3339()),
+    })
+}
+
+fn dead_letter_status(dead_letter: &DeadLetter) -> serde_json::Value {
+    serde_json::json!({
+        "id": dead_letter.request_id,
+        "status": "dead_lettered",
+        "dead_letter_id": dead_letter.id,
+        "attempts": dead_letter.request.attempts,
+        "last_status": dead_letter.last_status,
+        "dead_lettered_at": dead_letter.dead_lettered_at.to_rfc3339(),
+    })
+}
+
+// answer CORS preflight requests for any path
+#[options("/<_path..>")]
+fn preflight(_path: Segments<'_, Path>) -> Status {
+    Status::NoContent
 }
 
 // // // // // // // // // // // // // // // // // // // // // // // //
@@ -120,10 +518,9 @@ impl Fairing for CORS {
         response.set_header(Header::new("Access-Control-Allow-Origin", "*"));
         response.set_header(Header::new(
             "Access-Control-Allow-Methods",
-            "POST, GET, PATCH, OPTIONS",
+            "POST, GET, PUT, PATCH, DELETE, OPTIONS",
         ));
         response.set_header(Header::new("Access-Control-Allow-Headers", "*"));
-        response.set_status(Status::new(200));
     }
 }
 
@@ -137,9 +534,27 @@ pub async fn main() {
         .add_source(config::File::with_name("config/Settings"))
         .build()
         .unwrap();
+    // top level values, tables such as [headers] are loaded separately below
     let settings_map = settings
-        .try_deserialize::<HashMap<String, String>>()
-        .unwrap();
+        .clone()
+        .try_deserialize::<HashMap<String, config::Value>>()
+        .unwrap()
+        .into_iter()
+        .filter_map(|(key, value)| value.into_string().ok().map(|value| (key, value)))
+        .collect::<HashMap<String, String>>();
+    let header_policy = settings.get::<HeaderPolicy>("headers").unwrap_or_default();
+    let webhook_plugins = load_plugins(&settings);
+    let mut route_table = RouteTable::new(
+        settings
+            .get::<HashMap<String, RouteConfig>>("routes")
+            .unwrap_or_default(),
+        settings
+            .get::<BodyLimits>("body_limits")
+            .unwrap_or_default(),
+        settings
+            .get::<Vec<PriorityRule>>("priority_rules")
+            .unwrap_or_default(),
+    );
 
     let reque_port = settings_map
         .get("reque_service_port")
@@ -147,22 +562,6 @@ pub async fn main() {
         .parse::<u16>()
         .expect("cannot parse service port");
 
-    let require_success = settings_map
-        .clone()
-        .get("require_success")
-        .expect("could not find require_success")
-        .to_string()
-        .parse::<bool>()
-        .unwrap();
-
-    let remove_from_queue_on_failure = settings_map
-        .clone()
-        .get("remove_from_queue_on_failure")
-        .expect("could not find remove_from_queue_on_failure")
-        .to_string()
-        .parse::<bool>()
-        .unwrap();
-
     let config = rocket::Config {
         port: reque_port,
         address: std::net::Ipv4Addr::new(0, 0, 0, 0).into(),
@@ -187,76 +586,41 @@ pub async fn main() {
 
     println!("{}", database_url);
 
-    let interval_pool = MySqlPool::connect(database_url)
+    // start the dispatcher workers that send requests slowly
+    let dispatch_pool = MySqlPool::connect(database_url)
         .await
         .expect("database connection");
-
-    // start re-occuring task to send requests slowly
-    let http_dest: String = settings_map.get("http_dest").unwrap().to_string();
-    let http_proto: String = settings_map.get("http_proto").unwrap().to_string();
-    let reque_interval: u64 = settings_map
-        .get("reque_interval")
-        .unwrap()
-        .to_string()
-        .parse::<u64>()
-        .unwrap();
-    tokio::spawn(async move {
-        let start = Instant::now();
-        let mut interval = interval_at(start, tokio::time::Duration::from_secs(reque_interval));
-
-        loop {
-            interval.tick().await;
-            let out = sqlx::query("SELECT * FROM requests ORDER BY id ASC")
-                .fetch_one(&interval_pool.clone())
-                .await;
-            // let mut method: String = String::new(); // filter incoming by method in the future?
-            // let mut host: String = String::new(); // filter by host in the future?
-
-            let mut id: i64 = 0;
-            println!("{}", id);
-            let mut method: String = String::new();
-            println!("{}", method);
-            let mut host: String = String::new();
-            println!("{}", host);
-            let mut uri: String = String::new();
-            println!("{}", uri);
-            let mut body: String = String::new();
-            println!("{}", body);
-
-            let out_ok = out.is_ok();
-            if out_ok {
-                let out_bind = out.expect("could not get row");
-
-                id = out_bind.get("id");
-                method = out_bind.get("method");
-                uri = out_bind.get("uri");
-                body = out_bind.get("body");
-                host = out_bind.get("body");
-
-                println!("{}", id);
-                println!("{}", method);
-                println!("{}", host);
-                println!("{}", uri);
-                println!("{}", body);
-
-                let send_success = send_stored_request(
-                    http_proto.clone(),
-                    http_dest.clone(),
-                    uri.to_string(),
-                    body.to_string(),
-                )
-                .await;
-
-                if send_success && require_success {
-                    println!("Deleting Request: {} - {}", uri, body);
-                    delete_request_from_db(id, &interval_pool).await;
-                }
-                if remove_from_queue_on_failure {
-                    delete_request_from_db(id, &interval_pool).await;
-                }
-            }
-        }
-    });
+    // create the tables, or bring an existing database up to date, before anything reads them
+    sqlx::migrate!()
+        .run(&dispatch_pool)
+        .await
+        .expect("database migration");
+    let destinations = settings
+        .get::<HashMap<String, DestinationConfig>>("destinations")
+        .unwrap_or_default();
+    route_table.resolve_unknown_destinations(&destinations);
+    let mut dispatcher_config = DispatcherConfig::from_settings(&settings_map);
+    dispatcher_config.outcome = settings.get::<OutcomePolicy>("outcome").unwrap_or_default();
+    dispatcher_config.circuit_breaker = settings
+        .get::<CircuitBreakerConfig>("circuit_breaker")
+        .unwrap_or_default();
+    // long-lived http clients, one per destination, shared by every delivery
+    let clients = Arc::new(build_clients(
+        &destinations,
+        &settings
+            .get::<ClientConfig>("http_client")
+            .unwrap_or_default(),
+        dispatcher_config.delivery_timeout_secs,
+    ));
+    let dispatcher = Dispatcher::new(
+        dispatcher_config,
+        dispatch_pool,
+        route_table.clone(),
+        destinations.clone(),
+        clients.clone(),
+    );
+    let circuit_breakers = dispatcher.circuit_breakers();
+    dispatcher.spawn().await;
 
     // initialize database connection
     let pool = MySqlPool::connect(database_url)
@@ -264,34 +628,102 @@ pub async fn main() {
         .expect("database connection");
 
     // launch Rocket
-    custom(&config)
+    let mut rocket = custom(&config)
         .manage(settings_map.clone())
         .manage::<MySqlPool>(pool)
-        .mount("/", routes![index, shopify_webhook, slow_test_server])
-        .attach(CORS)
-        .launch()
-        .await
-        .unwrap();
+        .manage(header_policy)
+        .manage(route_table.clone())
+        .manage(destinations)
+        .manage(circuit_breakers)
+        .manage(clients)
+        .mount(
+            "/",
+            routes![
+                index,
+                slow_test_server,
+                catch_all_get,
+                catch_all_post,
+                catch_all_put,
+                catch_all_patch,
+                catch_all_delete,
+                request_status,
+                preflight
+            ],
+        )
+        .mount(
+            "/",
+            routes![
+                admin::dead_letters::list_dead_letters,
+                admin::dead_letters::get_dead_letter,
+                admin::dead_letters::requeue_dead_letter,
+                admin::dead_letters::purge_dead_letter,
+                admin::dead_letters::purge_dead_letters,
+                admin::delivery_attempts::list_delivery_attempts,
+                admin::delivery_attempts::get_delivery_attempt,
+                admin::destinations::list_destinations
+            ],
+        )
+        .attach(CORS);
+    // mount each enabled webhook plugin at its own path
+    for plugin in webhook_plugins {
+        info!(
+            "mounting {} webhook plugin at {}",
+            plugin.name(),
+            plugin.mount_path()
+        );
+        rocket = rocket.mount(plugin.mount_path(), PluginHandler::routes(plugin));
+    }
+    rocket.launch().await.unwrap();
+}
+
+// 202 response for every queued request
+#[derive(Responder)]
+#[response(status = 202, content_type = "json")]
+struct Accepted {
+    body: String,
+    location: Header<'static>,
+}
+
+impl Accepted {
+    fn new(id: i64, duplicate: bool) -> Accepted {
+        let location = uri!(request_status(id)).to_string();
+        Accepted {
+            body: serde_json::json!({
+                "id": id,
+                "status": "queued",
+                "duplicate": duplicate,
+                "location": location,
+            })
+            .to_string(),
+            location: Header::new("Location", location),
+        }
+    }
 }
 
 // The following impl's are for easy conversion of error types.
 #[derive(Responder)]
-#[response(status = 500, content_type = "json")]
+#[response(content_type = "json")]
 struct ErrorResponder {
-    message: String,
+    message: (Status, String),
 }
 
-impl From<anyhow::Error> for ErrorResponder {
-    fn from(err: anyhow::Error) -> ErrorResponder {
+impl ErrorResponder {
+    fn with_status(status: Status, message: &str) -> ErrorResponder {
         ErrorResponder {
-            message: err.to_string(),
+            message: (status, serde_json::json!({ "error": message }).to_string()),
         }
     }
 }
 
+impl From<anyhow::Error> for ErrorResponder {
+    fn from(err: anyhow::Error) -> ErrorResponder {
+        ErrorResponder::with_status(Status::InternalServerError, &err.to_string())
+    }
+}
+
 impl From<String> for ErrorResponder {
     fn from(string: String) -> ErrorResponder {
-        ErrorResponder { message: string }
+        ErrorResponder::with_status(Status::InternalServerError, &string)
     }
 }
 
@@ -0,0 +1,158 @@
+use crate::entities::dead_letter::{DeadLetter, SUMMARY_COLUMNS};
+use crate::entities::delivery_result::DeliveryResult;
+use crate::entities::storedrequest::StoredRequest;
+use crate::manage_requests::request_funcs::{
+    insert_request, lock_fanout_parent, remove_finished_parent, APPEND_FAILURE,
+};
+use anyhow::anyhow;
+use serde_json::Value;
+use sqlx::{MySql, Pool};
+
+// move a request that exhausted its retries out of the queue, with its final failure,
+// unless its lease expired and another worker claimed it since
+pub async fn dead_letter_request(
+    id: i64,
+    claim_token: &str,
+    result: &DeliveryResult,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<()> {
+    let mut tx = pool.begin().await?;
+    let parent_id = lock_fanout_parent(id, &mut tx).await?;
+    let updated = sqlx::query(&format!(
+        "UPDATE requests SET attempts = attempts + 1, last_error = ?, {}
+        WHERE (id)=? AND claim_token = ?",
+        APPEND_FAILURE
+    ))
+    .bind(result.describe())
+    .bind(result.status)
+    .bind(result.describe())
+    .bind(id)
+    .bind(claim_token)
+    .execute(&mut tx)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Err(anyhow!("claim on request {} was lost", id));
+    }
+    // fan-out deliveries take the body of their parent
+    sqlx::query(
+        "INSERT INTO dead_letters (request_id, parent_id, method, host, port, uri, headers, body,
+        content_type, content_encoding, route, destination, priority, deliver_at, partition_key, attempts,
+        failure_history, last_status, last_response)
+        SELECT r.id, r.parent_id, r.method, r.host, r.port, r.uri, r.headers, COALESCE(p.body, r.body),
+        r.content_type, r.content_encoding, r.route, r.destination, r.priority, r.deliver_at,
+        r.partition_key, r.attempts, r.failure_history, ?, ? FROM requests r LEFT JOIN requests p ON p.id = r.parent_id
+        WHERE r.id = ?",
+    )
+    .bind(result.status)
+    .bind(&result.response_body)
+    .bind(id)
+    .execute(&mut tx)
+    .await?;
+    sqlx::query("DELETE FROM requests WHERE (id)=?")
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    remove_finished_parent(parent_id, &mut tx).await?;
+    tx.commit().await?;
+    Ok(())
+}
+
+// summaries of dead letters, without their bodies
+pub async fn list_dead_letters(
+    limit: i64,
+    offset: i64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Vec<Value>> {
+    let rows = sqlx::query(&format!(
+        "SELECT {} FROM dead_letters ORDER BY id ASC LIMIT ? OFFSET ?",
+        SUMMARY_COLUMNS
+    ))
+    .bind(limit)
+    .bind(offset)
+    .fetch_all(pool)
+    .await?;
+    Ok(rows.iter().map(DeadLetter::summary_from_row).collect())
+}
+
+pub async fn get_dead_letter(id: i64, pool: &Pool<MySql>) -> anyhow::Result<Option<DeadLetter>> {
+    let row = sqlx::query("SELECT * FROM dead_letters WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(DeadLetter::from_row))
+}
+
+// the dead letter for a request id, used by the request status endpoint
+pub async fn find_dead_letter_by_request(
+    request_id: i64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Option<DeadLetter>> {
+    let row = sqlx::query("SELECT * FROM dead_letters WHERE request_id = ?")
+        .bind(request_id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(DeadLetter::from_row))
+}
+
+// dead lettered deliveries of a fan-out request
+pub async fn find_dead_letters_by_parent(
+    parent_id: i64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Vec<DeadLetter>> {
+    let rows = sqlx::query("SELECT * FROM dead_letters WHERE parent_id = ? ORDER BY id ASC")
+        .bind(parent_id)
+        .fetch_all(pool)
+        .await?;
+    Ok(rows.iter().map(DeadLetter::from_row).collect())
+}
+
+// put a dead letter back on the queue as a new request with fresh attempts,
+// optionally with an edited body or destination, returning the new request id
+pub async fn requeue_dead_letter(
+    id: i64,
+    body: Option<Vec<u8>>,
+    destination: Option<String>,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Option<i64>> {
+    let mut tx = pool.begin().await?;
+    let Some(row) = sqlx::query("SELECT * FROM dead_letters WHERE id = ? FOR UPDATE")
+        .bind(id)
+        .fetch_optional(&mut tx)
+        .await?
+    else {
+        return Ok(None);
+    };
+
+    let mut request = StoredRequest::from_row(&row);
+    if let Some(body) = body {
+        request.body = body;
+    }
+    if destination.is_some() {
+        request.destination = destination;
+    }
+    // redrive right away, the original delivery time has passed, as a request of its own
+    request.deliver_at = None;
+    request.parent_id = None;
+    let request_id = insert_request(&request, &mut tx).await?;
+    sqlx::query("DELETE FROM dead_letters WHERE id = ?")
+        .bind(id)
+        .execute(&mut tx)
+        .await?;
+    tx.commit().await?;
+    Ok(Some(request_id))
+}
+
+pub async fn purge_dead_letter(id: i64, pool: &Pool<MySql>) -> anyhow::Result<bool> {
+    let deleted = sqlx::query("DELETE FROM dead_letters WHERE id = ?")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(deleted.rows_affected() > 0)
+}
+
+pub async fn purge_dead_letters(pool: &Pool<MySql>) -> anyhow::Result<u64> {
+    let deleted = sqlx::query("DELETE FROM dead_letters")
+        .execute(pool)
+        .await?;
+    Ok(deleted.rows_affected())
+}
@@ -0,0 +1,79 @@
+use crate::entities::delivery_attempt::DeliveryAttempt;
+use crate::entities::delivery_result::DeliveryResult;
+use sqlx::{MySql, Pool, QueryBuilder};
+
+// record one delivery attempt, pruning attempts older than the retention a batch at a time
+pub async fn record_delivery_attempt(
+    request_id: i64,
+    attempt: i32,
+    destination: &str,
+    outcome: &str,
+    result: &DeliveryResult,
+    retention_days: u64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<()> {
+    sqlx::query(
+        "INSERT INTO delivery_attempts (request_id, attempt, destination, started_at, finished_at,
+        latency_ms, status, outcome, error_kind, error, response_headers, response_body)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
+    )
+    .bind(request_id)
+    .bind(attempt)
+    .bind(destination)
+    .bind(result.started_at)
+    .bind(result.finished_at)
+    .bind(result.latency.map(|l| l.as_millis() as u64))
+    .bind(result.status)
+    .bind(outcome)
+    .bind(result.transport_error.map(|e| e.kind()))
+    .bind(&result.error)
+    .bind(&result.response_headers)
+    .bind(&result.response_body)
+    .execute(pool)
+    .await?;
+    if retention_days > 0 {
+        sqlx::query(
+            "DELETE FROM delivery_attempts WHERE started_at < NOW() - INTERVAL ? DAY LIMIT 100",
+        )
+        .bind(retention_days)
+        .execute(pool)
+        .await?;
+    }
+    Ok(())
+}
+
+// newest attempts first, optionally only those of one request or destination
+pub async fn list_delivery_attempts(
+    request_id: Option<i64>,
+    destination: Option<&str>,
+    limit: i64,
+    offset: i64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Vec<DeliveryAttempt>> {
+    // only the filters that are set go into the query, so it can use the request_id or destination index
+    let mut query = QueryBuilder::<MySql>::new("SELECT * FROM delivery_attempts WHERE TRUE");
+    if let Some(request_id) = request_id {
+        query.push(" AND request_id = ").push_bind(request_id);
+    }
+    if let Some(destination) = destination {
+        query.push(" AND destination = ").push_bind(destination);
+    }
+    query
+        .push(" ORDER BY id DESC LIMIT ")
+        .push_bind(limit)
+        .push(" OFFSET ")
+        .push_bind(offset);
+    let rows = query.build().fetch_all(pool).await?;
+    Ok(rows.iter().map(DeliveryAttempt::from_row).collect())
+}
+
+pub async fn get_delivery_attempt(
+    id: i64,
+    pool: &Pool<MySql>,
+) -> anyhow::Result<Option<DeliveryAttempt>> {
+    let row = sqlx::query("SELECT * FROM delivery_attempts WHERE id = ?")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(row.as_ref().map(DeliveryAttempt::from_row))
+}
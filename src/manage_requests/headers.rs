@@ -0,0 +1,94 @@
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+// header name -> every value received for it, in order
+pub type StoredHeaders = BTreeMap<String, Vec<String>>;
+
+// hop-by-hop headers only apply to a single connection and are never stored
+const HOP_BY_HOP: [&str; 9] = [
+    "connection",
+    "keep-alive",
+    "proxy-authenticate",
+    "proxy-authorization",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+// recomputed by the client when the request is replayed
+const RECOMPUTED: [&str; 2] = ["host", "content-length"];
+// X-Reque-* headers instruct reQue itself and are never passed on
+const RESERVED_PREFIX: &str = "x-reque-";
+
+// [headers] table in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct HeaderPolicy {
+    #[serde(default)]
+    pub allow: Vec<String>, // when non-empty, only these headers are stored and replayed
+    #[serde(default)]
+    pub deny: Vec<String>, // these headers are never stored or replayed
+}
+
+impl HeaderPolicy {
+    fn permits(&self, name: &str) -> bool {
+        let allowed =
+            self.allow.is_empty() || self.allow.iter().any(|a| a.eq_ignore_ascii_case(name));
+        let denied = self.deny.iter().any(|d| d.eq_ignore_ascii_case(name));
+        allowed && !denied
+    }
+}
+
+// filter incoming headers down to the ones that should be stored and replayed
+pub fn filter_headers(headers: &HeaderMap<'_>, policy: &HeaderPolicy) -> StoredHeaders {
+    // headers listed in the Connection header are hop-by-hop as well
+    let connection_listed: Vec<String> = headers
+        .get("connection")
+        .flat_map(|v| v.split(','))
+        .map(|v| v.trim().to_lowercase())
+        .collect();
+
+    let mut stored = StoredHeaders::new();
+    for header in headers.iter() {
+        let name = header.name().as_str().to_lowercase();
+        if HOP_BY_HOP.contains(&name.as_str())
+            || RECOMPUTED.contains(&name.as_str())
+            || name.starts_with(RESERVED_PREFIX)
+            || connection_listed.contains(&name)
+            || !policy.permits(&name)
+        {
+            continue;
+        }
+        stored
+            .entry(name)
+            .or_default()
+            .push(header.value().to_string());
+    }
+    stored
+}
+
+pub fn serialize_headers(headers: &StoredHeaders) -> String {
+    serde_json::to_string(headers).unwrap_or_else(|_| "{}".to_string())
+}
+
+// rows queued before headers were persisted hold an empty string
+pub fn deserialize_headers(headers: &str) -> StoredHeaders {
+    serde_json::from_str(headers).unwrap_or_default()
+}
+
+// build the outbound header map for reqwest, skipping anything that is not a valid header
+pub fn to_reqwest_headers(headers: &StoredHeaders) -> reqwest::header::HeaderMap {
+    let mut out = reqwest::header::HeaderMap::new();
+    for (name, values) in headers {
+        let Ok(name) = reqwest::header::HeaderName::from_bytes(name.as_bytes()) else {
+            continue;
+        };
+        for value in values {
+            if let Ok(value) = reqwest::header::HeaderValue::from_str(value) {
+                out.append(name.clone(), value);
+            }
+        }
+    }
+    out
+}
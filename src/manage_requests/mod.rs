@@ -1 +1,4 @@
+pub mod dead_letter_funcs;
+pub mod delivery_attempt_funcs;
+pub mod headers;
 pub mod request_funcs;
@@ -1,53 +1,644 @@
+use crate::dispatcher::batch::{BatchConfig, BATCH_RESPONSE_LIMIT};
+use crate::dispatcher::http_client::DestinationClient;
+use crate::entities::delivery_result::{DeliveryResult, TransportError, RESPONSE_BODY_LIMIT};
 use crate::entities::storedrequest::StoredRequest;
-use sqlx::{MySql, MySqlPool, Pool};
+use crate::manage_requests::headers::{serialize_headers, to_reqwest_headers};
+use crate::routing::destinations::DEFAULT_DESTINATION;
+use crate::routing::route_table::RouteConfig;
+use anyhow::anyhow;
+use chrono::Utc;
+use log::{debug, info, warn};
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_ENCODING, CONTENT_TYPE, RETRY_AFTER};
+use reqwest::{Method, Url};
+use sha2::{Digest, Sha256};
+use sqlx::{Executor, MySql, MySqlPool, Pool, Row, Transaction};
+use std::time::{Duration, Instant};
 
-// write created request to db
-pub async fn write_request_to_db(request: StoredRequest<'_>, pool: &rocket::State<MySqlPool>) {
+// write created request to db, returning its id
+pub async fn write_request_to_db(
+    request: StoredRequest,
+    fanout: &[String],
+    pool: &rocket::State<MySqlPool>,
+) -> anyhow::Result<i64> {
+    let mut tx = pool.begin().await?;
+    let id = insert_queued(&request, fanout, &mut tx).await?;
+    tx.commit().await?;
+    Ok(id)
+}
+
+// write created request to db unless the idempotency key was already seen inside the window,
+// returning the id of the queued request and whether it is a repeat
+pub async fn write_idempotent_request_to_db(
+    request: StoredRequest,
+    fanout: &[String],
+    idempotency_key: &str,
+    window_secs: u64,
+    pool: &rocket::State<MySqlPool>,
+) -> anyhow::Result<(i64, bool)> {
+    let key_hash = hex::encode(Sha256::digest(idempotency_key.as_bytes()));
+
+    // forget expired keys, each key keeps the window of the route it was queued on
+    sqlx::query("DELETE FROM idempotency_keys WHERE expires_at < NOW()")
+        .execute(&**pool)
+        .await?;
+    if let Some(id) = find_idempotent_request(&key_hash, pool).await? {
+        return Ok((id, true));
+    }
+
+    // the unique key decides between concurrent repeats, the loser rolls back its insert
+    let mut tx = pool.begin().await?;
+    let id = insert_queued(&request, fanout, &mut tx).await?;
+    let claimed = sqlx::query(
+        "INSERT IGNORE INTO idempotency_keys (key_hash, request_id, expires_at)
+            VALUES (?, ?, NOW() + INTERVAL ? SECOND)",
+    )
+    .bind(&key_hash)
+    .bind(id)
+    .bind(window_secs)
+    .execute(&mut tx)
+    .await?
+    .rows_affected()
+        == 1;
+    if !claimed {
+        tx.rollback().await?;
+        let id = find_idempotent_request(&key_hash, pool)
+            .await?
+            .ok_or_else(|| anyhow!("idempotency key expired while queueing"))?;
+        return Ok((id, true));
+    }
+    tx.commit().await?;
+    Ok((id, false))
+}
+
+async fn find_idempotent_request(key_hash: &str, pool: &MySqlPool) -> anyhow::Result<Option<i64>> {
+    let row = sqlx::query(
+        "SELECT request_id FROM idempotency_keys WHERE key_hash = ? AND expires_at >= NOW()",
+    )
+    .bind(key_hash)
+    .fetch_optional(pool)
+    .await?;
+    Ok(row.map(|row| row.get("request_id")))
+}
+
+// queue a request, or with two or more fan-out destinations a parent row holding the body
+// that is never claimed itself, and one delivery per destination tracked on its own row
+async fn insert_queued(
+    request: &StoredRequest,
+    fanout: &[String],
+    tx: &mut Transaction<'_, MySql>,
+) -> Result<i64, sqlx::Error> {
+    if fanout.len() < 2 {
+        return insert_request(request, &mut *tx).await;
+    }
+    let parent_id = insert_request(request, &mut *tx).await?;
+    sqlx::query("UPDATE requests SET fanout = 1 WHERE (id)=?")
+        .bind(parent_id)
+        .execute(&mut *tx)
+        .await?;
+    for destination in fanout {
+        let delivery = StoredRequest {
+            body: Vec::new(),
+            destination: Some(destination.clone()),
+            parent_id: Some(parent_id),
+            ..request.clone()
+        };
+        insert_request(&delivery, &mut *tx).await?;
+    }
+    Ok(parent_id)
+}
+
+pub async fn insert_request<'e, E>(request: &StoredRequest, executor: E) -> Result<i64, sqlx::Error>
+where
+    E: Executor<'e, Database = MySql>,
+{
     let req = request.clone();
-    let _insert = sqlx::query(
-        "INSERT INTO requests (method, host, port, uri, headers, body)
-        VALUES (?, ?, ?, ?, ?, ?)",
+    let insert = sqlx::query(
+        "INSERT INTO requests (method, host, port, uri, headers, body, content_type, content_encoding, route, destination, priority, effective_priority, deliver_at, parent_id, partition_key)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)",
     )
     .bind(req.method)
     .bind(req.host)
     .bind(req.port)
     .bind(req.uri)
-    .bind("")
+    .bind(serialize_headers(&req.headers))
     .bind(req.body)
-    .execute(&**pool)
-    .await
-    .unwrap();
+    .bind(req.content_type)
+    .bind(req.content_encoding)
+    .bind(req.route)
+    .bind(req.destination)
+    .bind(req.priority)
+    .bind(req.priority)
+    .bind(req.deliver_at)
+    .bind(req.parent_id)
+    .bind(req.partition_key)
+    .execute(executor)
+    .await?;
+    Ok(insert.last_insert_id() as i64)
 }
 
-pub async fn delete_request_from_db(id: i64, pool: &Pool<MySql>) {
-    let _delete = sqlx::query("DELETE FROM requests WHERE (id)=?")
+// delete a delivered request, unless its lease expired and another worker claimed it since
+pub async fn delete_request_from_db(
+    id: i64,
+    claim_token: &str,
+    pool: &Pool<MySql>,
+) -> Result<(), sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    let parent_id = lock_fanout_parent(id, &mut tx).await?;
+    sqlx::query("DELETE FROM requests WHERE (id)=? AND claim_token = ?")
         .bind(id)
-        .execute(pool)
+        .bind(claim_token)
+        .execute(&mut tx)
+        .await?;
+    remove_finished_parent(parent_id, &mut tx).await?;
+    tx.commit().await
+}
+
+// lock the fan-out parent of a delivery before finishing it,
+// so sibling deliveries finishing at the same time see each other's deletes
+pub async fn lock_fanout_parent(
+    id: i64,
+    tx: &mut Transaction<'_, MySql>,
+) -> Result<Option<i64>, sqlx::Error> {
+    let parent_id = sqlx::query("SELECT parent_id FROM requests WHERE (id)=?")
+        .bind(id)
+        .fetch_optional(&mut *tx)
+        .await?
+        .and_then(|row| row.get::<Option<i64>, _>("parent_id"));
+    if let Some(parent_id) = parent_id {
+        sqlx::query("SELECT id FROM requests WHERE (id)=? FOR UPDATE")
+            .bind(parent_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(parent_id)
+}
+
+// the fan-out parent is removed once every delivery succeeded or failed permanently,
+// the locking read sees deliveries finished by other workers since the transaction began
+pub async fn remove_finished_parent(
+    parent_id: Option<i64>,
+    tx: &mut Transaction<'_, MySql>,
+) -> Result<(), sqlx::Error> {
+    let Some(parent_id) = parent_id else {
+        return Ok(());
+    };
+    let remaining: i64 =
+        sqlx::query("SELECT COUNT(*) AS remaining FROM requests WHERE parent_id = ? FOR UPDATE")
+            .bind(parent_id)
+            .fetch_one(&mut *tx)
+            .await?
+            .get("remaining");
+    if remaining == 0 {
+        sqlx::query("DELETE FROM requests WHERE (id)=? AND fanout = 1")
+            .bind(parent_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    Ok(())
+}
+
+// requests aged per statement, so an aging pass never holds many row locks at once
+const AGING_BATCH: u64 = 1000;
+
+// candidates read beyond the requested number, for when concurrent claimers take the first ones
+const CLAIM_SPARE_CANDIDATES: usize = 4;
+
+// claim the highest priority due request that is unclaimed or whose lease expired, so no other
+// worker or replica delivers it
+pub async fn claim_next_request(
+    claim_token: &str,
+    lease_secs: u64,
+    paused: &[String], // destinations that cannot take a delivery right now
+    pool: &Pool<MySql>,
+) -> Option<(i64, StoredRequest)> {
+    claim_requests(claim_token, lease_secs, paused, None, 1, pool)
         .await
-        .unwrap();
+        .pop()
+}
+
+// claim up to limit due requests for one destination under the same claim token, to fill a batch
+pub async fn claim_batch_members(
+    claim_token: &str,
+    lease_secs: u64,
+    destination: &str,
+    limit: usize,
+    pool: &Pool<MySql>,
+) -> Vec<(i64, StoredRequest)> {
+    claim_requests(claim_token, lease_secs, &[], Some(destination), limit, pool).await
+}
+
+async fn claim_requests(
+    claim_token: &str,
+    lease_secs: u64,
+    paused: &[String],
+    destination: Option<&str>,
+    limit: usize,
+    pool: &Pool<MySql>,
+) -> Vec<(i64, StoredRequest)> {
+    let skip_paused = match paused.len() {
+        0 => String::new(),
+        n => format!(
+            "AND COALESCE(destination, '{}') NOT IN ({})",
+            DEFAULT_DESTINATION,
+            vec!["?"; n].join(", ")
+        ),
+    };
+    let only_destination = match destination {
+        Some(_) => format!("AND COALESCE(destination, '{}') = ?", DEFAULT_DESTINATION),
+        None => String::new(),
+    };
+    // candidates come from a plain read that takes no locks, so concurrent claimers never wait on
+    // each other's scans; each candidate is then claimed by primary key and the first claimer wins.
+    // A partitioned request waits until every earlier request of its partition and destination
+    // has left the queue, other partitions carry on past it
+    let query = format!(
+        "SELECT id, claim_token FROM requests r
+        WHERE fanout = 0 AND (claim_token IS NULL OR lease_expires_at <= NOW())
+        AND (next_attempt_at IS NULL OR next_attempt_at <= NOW())
+        AND (deliver_at IS NULL OR deliver_at <= NOW()) {} {}
+        AND (partition_key IS NULL OR NOT EXISTS (
+            SELECT 1 FROM requests earlier WHERE earlier.partition_key = r.partition_key
+            AND earlier.destination <=> r.destination AND earlier.fanout = 0 AND earlier.id < r.id))
+        ORDER BY effective_priority DESC, id ASC LIMIT ?",
+        skip_paused, only_destination
+    );
+    let candidates = paused
+        .iter()
+        .map(String::as_str)
+        .chain(destination)
+        .fold(sqlx::query(&query), |query, destination| {
+            query.bind(destination)
+        })
+        // a few spare candidates for the ones other claimers take first
+        .bind((limit + CLAIM_SPARE_CANDIDATES) as u64)
+        .fetch_all(pool)
+        .await;
+    let candidates = match candidates {
+        Ok(candidates) => candidates,
+        Err(e) => {
+            warn!("could not look for requests to claim - {}", e);
+            return Vec::new();
+        }
+    };
+
+    let mut claimed = Vec::with_capacity(limit);
+    for candidate in candidates {
+        if claimed.len() >= limit {
+            break;
+        }
+        let id: i64 = candidate.get("id");
+        match claim_request(id, claim_token, lease_secs, pool).await {
+            Ok(Some(stored)) => {
+                if candidate.get::<Option<String>, _>("claim_token").is_some() {
+                    info!(target:"app::requests", "lease on request {} expired, claimed it again", id);
+                }
+                claimed.push((id, stored));
+            }
+            Ok(None) => {} // another worker or replica claimed it first
+            Err(e) => warn!("could not claim request {} - {}", id, e),
+        }
+    }
+    claimed
+}
+
+// claim one request by primary key, none when it was claimed or finished since it was read
+async fn claim_request(
+    id: i64,
+    claim_token: &str,
+    lease_secs: u64,
+    pool: &Pool<MySql>,
+) -> Result<Option<StoredRequest>, sqlx::Error> {
+    let updated = sqlx::query(
+        "UPDATE requests SET claim_token = ?, claimed_at = NOW(),
+        lease_expires_at = NOW() + INTERVAL ? SECOND
+        WHERE (id)=? AND fanout = 0 AND (claim_token IS NULL OR lease_expires_at <= NOW())
+        AND (next_attempt_at IS NULL OR next_attempt_at <= NOW())",
+    )
+    .bind(claim_token)
+    .bind(lease_secs)
+    .bind(id)
+    .execute(pool)
+    .await?;
+    if updated.rows_affected() == 0 {
+        return Ok(None);
+    }
+    let Some(row) = sqlx::query("SELECT * FROM requests WHERE (id)=? AND claim_token = ?")
+        .bind(id)
+        .bind(claim_token)
+        .fetch_optional(pool)
+        .await?
+    else {
+        return Ok(None);
+    };
+    let mut stored = StoredRequest::from_row(&row);
+    // fan-out deliveries share the body of their parent
+    if let Some(parent_id) = stored.parent_id {
+        stored.body = sqlx::query("SELECT body FROM requests WHERE (id)=?")
+            .bind(parent_id)
+            .fetch_one(pool)
+            .await?
+            .get("body");
+    }
+    Ok(Some(stored))
+}
+
+// waiting requests gain one priority level every aging_secs, counted from when they became
+// deliverable, so low priority work still moves; done apart from claims, in short batches,
+// so the claim order stays an index walk. Returns the number of requests aged
+pub async fn age_priorities(aging_secs: u64, pool: &Pool<MySql>) -> Result<u64, sqlx::Error> {
+    let aged =
+        "priority + FLOOR(TIMESTAMPDIFF(SECOND, COALESCE(deliver_at, created_at), NOW()) / ?)";
+    let query = format!(
+        "UPDATE requests SET effective_priority = {}
+        WHERE fanout = 0 AND claim_token IS NULL AND effective_priority <> {}
+        LIMIT {}",
+        aged, aged, AGING_BATCH
+    );
+    let mut total = 0;
+    loop {
+        let updated = sqlx::query(&query)
+            .bind(aging_secs)
+            .bind(aging_secs)
+            .execute(pool)
+            .await?
+            .rows_affected();
+        total += updated;
+        if updated < AGING_BATCH {
+            return Ok(total);
+        }
+    }
+}
+
+// extend the lease right before sending, false when it already expired and the claim was lost
+pub async fn renew_lease(id: i64, claim_token: &str, lease_secs: u64, pool: &Pool<MySql>) -> bool {
+    sqlx::query(
+        "UPDATE requests SET lease_expires_at = NOW() + INTERVAL ? SECOND
+        WHERE (id)=? AND claim_token = ?",
+    )
+    .bind(lease_secs)
+    .bind(id)
+    .bind(claim_token)
+    .execute(pool)
+    .await
+    .map(|r| r.rows_affected() > 0)
+    .unwrap_or(false)
+}
+
+// hand a claimed request back without counting an attempt, it becomes deliverable again after the delay
+pub async fn release_claim(
+    id: i64,
+    claim_token: &str,
+    delay_secs: u64,
+    pool: &Pool<MySql>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(
+        "UPDATE requests SET next_attempt_at = NOW() + INTERVAL ? SECOND,
+        claim_token = NULL, claimed_at = NULL, lease_expires_at = NULL
+        WHERE (id)=? AND claim_token = ?",
+    )
+    .bind(delay_secs)
+    .bind(id)
+    .bind(claim_token)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+// appends {"at", "status", "error"} to the failure_history JSON array, binds status then error
+pub const APPEND_FAILURE: &str =
+    "failure_history = JSON_ARRAY_APPEND(COALESCE(failure_history, '[]'), '$',
+    JSON_OBJECT('at', NOW(), 'status', ?, 'error', ?))";
+
+// record a failed delivery and hand the request back to the queue once it is due again
+pub async fn schedule_retry(
+    id: i64,
+    claim_token: &str,
+    result: &DeliveryResult,
+    delay_secs: u64,
+    pool: &Pool<MySql>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query(&format!(
+        "UPDATE requests SET attempts = attempts + 1, last_error = ?, {},
+        next_attempt_at = NOW() + INTERVAL ? SECOND,
+        claim_token = NULL, claimed_at = NULL, lease_expires_at = NULL
+        WHERE (id)=? AND claim_token = ?",
+        APPEND_FAILURE
+    ))
+    .bind(result.describe())
+    .bind(result.status)
+    .bind(result.describe())
+    .bind(delay_secs)
+    .bind(id)
+    .bind(claim_token)
+    .execute(pool)
+    .await?;
+    Ok(())
 }
 
 pub async fn send_stored_request(
-    http_proto: String,
-    http_dest: String,
-    uri: String,
-    body: String,
-) -> bool {
-    let built_uri = format!("{}://{}{}", http_proto, http_dest, uri);
-    println!("Sending Request;\n{}", built_uri);
-    println!("{}\n", body);
-    let res = reqwest::Client::new()
-        .post(built_uri)
+    client: &DestinationClient,
+    base_url: &str,
+    request: &StoredRequest,
+    route: Option<&RouteConfig>,
+) -> DeliveryResult {
+    let built_uri = format!("{}{}", base_url.trim_end_matches('/'), request.uri);
+    let Ok(mut url) = Url::parse(&built_uri) else {
+        return invalid_uri(&built_uri);
+    };
+    let mut method = request.method.clone();
+    let mut headers = to_reqwest_headers(&request.headers);
+    let mut body = request.body.clone();
+
+    // the original content type and encoding are replayed even if the header policy drops them
+    if let Some(value) = request
+        .content_type
+        .as_deref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        headers.insert(CONTENT_TYPE, value);
+    }
+    if let Some(value) = request
+        .content_encoding
+        .as_deref()
+        .and_then(|v| HeaderValue::from_str(v).ok())
+    {
+        headers.insert(CONTENT_ENCODING, value);
+    }
+
+    // per-route delivery overrides
+    if let Some(route) = route {
+        if let Some(route_method) = &route.method {
+            method = route_method.clone();
+        }
+        if route.query_as_json_body {
+            body = query_to_json(&url).into_bytes();
+            url.set_query(None);
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+            headers.remove(CONTENT_ENCODING);
+        }
+    }
+    let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap_or(Method::POST);
+    send(client, method, url, headers, body, RESPONSE_BODY_LIMIT).await
+}
+
+// send the requests of a batch as one call to the destination's bulk endpoint
+pub async fn send_batch(
+    client: &DestinationClient,
+    base_url: &str,
+    batch: &BatchConfig,
+    members: &[&StoredRequest],
+) -> DeliveryResult {
+    let path = batch
+        .path
+        .as_deref()
+        .or_else(|| members.first().map(|m| m.uri.as_str()))
+        .unwrap_or("/");
+    let built_uri = format!("{}{}", base_url.trim_end_matches('/'), path);
+    let Ok(url) = Url::parse(&built_uri) else {
+        return invalid_uri(&built_uri);
+    };
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        CONTENT_TYPE,
+        HeaderValue::from_static(batch.envelope.content_type()),
+    );
+    let method = Method::from_bytes(batch.method.to_uppercase().as_bytes()).unwrap_or(Method::POST);
+    let body = batch.envelope.build(members);
+    send(client, method, url, headers, body, BATCH_RESPONSE_LIMIT).await
+}
+
+fn invalid_uri(built_uri: &str) -> DeliveryResult {
+    let now = Utc::now();
+    DeliveryResult {
+        status: None,
+        error: Some(format!("invalid destination uri: {}", built_uri)),
+        transport_error: Some(TransportError::Other),
+        response_body: None,
+        response_headers: None,
+        latency: None,
+        retry_after: None,
+        started_at: Some(now),
+        finished_at: Some(now),
+    }
+}
+
+async fn send(
+    client: &DestinationClient,
+    method: Method,
+    url: Url,
+    headers: HeaderMap,
+    body: Vec<u8>,
+    response_limit: usize,
+) -> DeliveryResult {
+    debug!(target:"app::requests", "sending {} {} - {} bytes", method, url, body.len());
+    let started_at = Utc::now();
+    let started = Instant::now();
+    let res = client
+        .client
+        .request(method, url)
+        .headers(headers)
         .body(body)
         .send()
         .await;
 
-    let mut success = false;
-    if res.is_ok() {
-        match res.unwrap().status().as_u16() {
-            200 => success = true,
-            _ => success = false,
+    match res {
+        Ok(res) => {
+            let latency = started.elapsed();
+            let status = res.status().as_u16();
+            let retry_after = match status {
+                429 | 503 => res
+                    .headers()
+                    .get(RETRY_AFTER)
+                    .and_then(|v| v.to_str().ok())
+                    .and_then(parse_retry_after),
+                _ => None,
+            };
+            let response_headers = format_headers(res.headers());
+            let response_body = read_response(res, client.read_timeout, response_limit).await;
+            DeliveryResult {
+                status: Some(status),
+                error: None,
+                transport_error: None,
+                response_body,
+                response_headers: Some(response_headers),
+                latency: Some(latency),
+                retry_after,
+                started_at: Some(started_at),
+                finished_at: Some(Utc::now()),
+            }
         }
+        Err(e) => DeliveryResult {
+            status: None,
+            error: Some(e.to_string()),
+            transport_error: Some(if e.is_timeout() {
+                TransportError::Timeout
+            } else if e.is_connect() {
+                TransportError::Connect
+            } else {
+                TransportError::Other
+            }),
+            response_body: None,
+            response_headers: None,
+            latency: Some(started.elapsed()),
+            retry_after: None,
+            started_at: Some(started_at),
+            finished_at: Some(Utc::now()),
+        },
     }
-    success
+}
+
+// response headers as "name: value" lines, kept to RESPONSE_BODY_LIMIT bytes
+fn format_headers(headers: &HeaderMap) -> String {
+    let mut formatted = String::new();
+    for (name, value) in headers {
+        let line = format!("{}: {}\n", name, String::from_utf8_lossy(value.as_bytes()));
+        if formatted.len() + line.len() > RESPONSE_BODY_LIMIT {
+            break;
+        }
+        formatted.push_str(&line);
+    }
+    formatted
+}
+
+// Retry-After is either a number of seconds or an HTTP date
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+    let at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+}
+
+// read the whole response so the connection can be reused, keeping the first limit bytes,
+// and give up when the destination goes quiet for longer than the read timeout
+async fn read_response(
+    mut res: reqwest::Response,
+    read_timeout: Duration,
+    limit: usize,
+) -> Option<String> {
+    let mut body = Vec::new();
+    loop {
+        match rocket::tokio::time::timeout(read_timeout, res.chunk()).await {
+            Ok(Ok(Some(chunk))) => {
+                let room = limit.saturating_sub(body.len());
+                body.extend_from_slice(&chunk[..chunk.len().min(room)]);
+            }
+            Ok(Ok(None)) => break,
+            Ok(Err(_)) | Err(_) if body.is_empty() => return None,
+            Ok(Err(_)) | Err(_) => break,
+        }
+    }
+    Some(String::from_utf8_lossy(&body).into_owned())
+}
+
+// query parameters as a JSON object, repeated parameters keep their last value
+fn query_to_json(url: &Url) -> String {
+    let params = url
+        .query_pairs()
+        .map(|(k, v)| (k.to_string(), serde_json::Value::String(v.to_string())))
+        .collect::<serde_json::Map<String, serde_json::Value>>();
+    serde_json::Value::Object(params).to_string()
 }
@@ -0,0 +1,105 @@
+use crate::plugins::signature::verify_hmac_sha256;
+use crate::plugins::WebhookPlugin;
+use config::Config;
+use log::warn;
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+
+// [plugins.github] table in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct GithubConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub secrets: Vec<String>, // list several while rotating
+}
+
+pub struct GithubPlugin {
+    config: GithubConfig,
+}
+
+impl GithubPlugin {
+    pub fn from_settings(settings: &Config) -> Option<GithubPlugin> {
+        let config = settings.get::<GithubConfig>("plugins.github").ok()?;
+        if !config.enabled {
+            return None;
+        }
+        if config.secrets.is_empty() {
+            warn!("no github secrets configured, every github webhook will be rejected");
+        }
+        Some(GithubPlugin { config })
+    }
+}
+
+impl WebhookPlugin for GithubPlugin {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    // X-Hub-Signature-256 is "sha256=" followed by the hex HMAC of the raw body
+    fn verify(&self, headers: &HeaderMap<'_>, body: &[u8]) -> bool {
+        match headers
+            .get_one("x-hub-signature-256")
+            .and_then(|h| h.trim().strip_prefix("sha256="))
+            .and_then(|h| hex::decode(h).ok())
+        {
+            Some(signature) => self
+                .config
+                .secrets
+                .iter()
+                .any(|secret| verify_hmac_sha256(secret, &[body], &signature)),
+            None => false,
+        }
+    }
+
+    fn event_type(&self, headers: &HeaderMap<'_>, _body: &[u8]) -> Option<String> {
+        headers.get_one("x-github-event").map(String::from)
+    }
+
+    fn dedup_key(&self, headers: &HeaderMap<'_>, _body: &[u8]) -> Option<String> {
+        headers.get_one("x-github-delivery").map(String::from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+
+    // example from GitHub's "Validating webhook deliveries" documentation
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    const SIGNATURE: &str =
+        "sha256=757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn plugin(secrets: &[&str]) -> GithubPlugin {
+        GithubPlugin {
+            config: GithubConfig {
+                enabled: true,
+                secrets: secrets.iter().map(|s| s.to_string()).collect(),
+            },
+        }
+    }
+
+    fn headers(signature: &str) -> HeaderMap<'static> {
+        let mut headers = HeaderMap::new();
+        headers.add(Header::new("X-Hub-Signature-256", signature.to_string()));
+        headers
+    }
+
+    #[test]
+    fn accepts_the_documented_signature() {
+        assert!(plugin(&[SECRET]).verify(&headers(SIGNATURE), BODY));
+        assert!(plugin(&["rotated", SECRET]).verify(&headers(SIGNATURE), BODY));
+    }
+
+    #[test]
+    fn rejects_wrong_secrets_bodies_and_headers() {
+        assert!(!plugin(&["wrong"]).verify(&headers(SIGNATURE), BODY));
+        assert!(!plugin(&[SECRET]).verify(&headers(SIGNATURE), b"Hello, World"));
+        let unprefixed = SIGNATURE.trim_start_matches("sha256=");
+        assert!(!plugin(&[SECRET]).verify(&headers(unprefixed), BODY));
+        assert!(!plugin(&[SECRET]).verify(&HeaderMap::new(), BODY));
+        assert!(!plugin(&[]).verify(&headers(SIGNATURE), BODY));
+    }
+}
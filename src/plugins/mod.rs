@@ -0,0 +1,147 @@
+pub mod github;
+pub mod shopify;
+pub mod signature;
+pub mod slack;
+pub mod stripe;
+
+use crate::fairings::de_construct_request::RRequest;
+use crate::manage_requests::headers::HeaderPolicy;
+use crate::routing::route_table::RouteTable;
+use crate::{queue_request, read_body, Accepted, ErrorResponder};
+use config::Config;
+use log::{info, warn};
+use rocket::data::Data;
+use rocket::http::{HeaderMap, Method, Status};
+use rocket::request::Request;
+use rocket::route::{Handler, Outcome, Route};
+use rocket::serde::json::Json;
+use rocket::State;
+use serde_json::Value;
+use sqlx::MySqlPool;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+// a webhook provider mounted in front of the queue, verifying requests before they are stored
+pub trait WebhookPlugin: Send + Sync {
+    // name used in logs and for the [plugins.<name>] settings table
+    fn name(&self) -> &'static str;
+
+    fn mount_path(&self) -> String {
+        format!("/plugins/{}", self.name())
+    }
+
+    // check the provider signature over the raw body
+    fn verify(&self, headers: &HeaderMap<'_>, body: &[u8]) -> bool;
+
+    fn event_type(&self, headers: &HeaderMap<'_>, body: &[u8]) -> Option<String>;
+
+    // provider assigned id that stays the same across retries of one event
+    fn dedup_key(&self, headers: &HeaderMap<'_>, body: &[u8]) -> Option<String>;
+
+    // a verified request the provider expects an answer to right away instead of queueing,
+    // such as a handshake challenge
+    fn immediate_response(&self, _headers: &HeaderMap<'_>, _body: &[u8]) -> Option<Value> {
+        None
+    }
+}
+
+// a queued webhook, or the answer to one that is not queued
+#[derive(Responder)]
+pub enum PluginResponse {
+    Queued(Accepted),
+    Immediate(Json<Value>),
+}
+
+// every plugin enabled in config/Settings.toml
+pub fn load_plugins(settings: &Config) -> Vec<Arc<dyn WebhookPlugin>> {
+    let mut plugins: Vec<Arc<dyn WebhookPlugin>> = vec![];
+    if let Some(plugin) = shopify::ShopifyPlugin::from_settings(settings) {
+        plugins.push(Arc::new(plugin));
+    }
+    if let Some(plugin) = github::GithubPlugin::from_settings(settings) {
+        plugins.push(Arc::new(plugin));
+    }
+    if let Some(plugin) = stripe::StripePlugin::from_settings(settings) {
+        plugins.push(Arc::new(plugin));
+    }
+    if let Some(plugin) = slack::SlackPlugin::from_settings(settings) {
+        plugins.push(Arc::new(plugin));
+    }
+    plugins
+}
+
+#[derive(Clone)]
+pub struct PluginHandler {
+    plugin: Arc<dyn WebhookPlugin>,
+    failures: Arc<AtomicU64>,
+}
+
+impl PluginHandler {
+    pub fn routes(plugin: Arc<dyn WebhookPlugin>) -> Vec<Route> {
+        let handler = PluginHandler {
+            plugin,
+            failures: Arc::new(AtomicU64::new(0)),
+        };
+        vec![Route::new(Method::Post, "/", handler)]
+    }
+
+    async fn ingest<'r>(
+        &self,
+        req: &'r Request<'_>,
+        data: Data<'r>,
+    ) -> Result<PluginResponse, ErrorResponder> {
+        let request = req
+            .guard::<RRequest<'_>>()
+            .await
+            .succeeded()
+            .ok_or("could not read request")?;
+        let (Some(pool), Some(header_policy), Some(route_table), Some(settings_map)) = (
+            State::<MySqlPool>::get(req.rocket()),
+            State::<HeaderPolicy>::get(req.rocket()),
+            State::<RouteTable>::get(req.rocket()),
+            State::<HashMap<String, String>>::get(req.rocket()),
+        ) else {
+            return Err("missing managed state".into());
+        };
+        let body = read_body(&request, data, route_table).await?;
+
+        let name = self.plugin.name();
+        if !self.plugin.verify(req.headers(), &body) {
+            let failures = self.failures.fetch_add(1, Ordering::Relaxed) + 1;
+            warn!(target:"app::requests", "{} webhook signature verification failed - total failures: {}", name, failures);
+            return Err(ErrorResponder::with_status(
+                Status::Unauthorized,
+                &format!("invalid {} signature", name),
+            ));
+        }
+
+        if let Some(response) = self.plugin.immediate_response(req.headers(), &body) {
+            info!(target:"app::requests", "{} webhook - answered without queueing", name);
+            return Ok(PluginResponse::Immediate(Json(response)));
+        }
+
+        let event_type = self.plugin.event_type(req.headers(), &body);
+        let dedup_key = self.plugin.dedup_key(req.headers(), &body);
+        info!(target:"app::requests", "{} webhook - event: {} - dedup key: {}", name, event_type.as_deref().unwrap_or("unknown"), dedup_key.as_deref().unwrap_or("none"));
+
+        queue_request(
+            request,
+            body,
+            pool,
+            header_policy,
+            route_table,
+            settings_map,
+            dedup_key,
+        )
+        .await
+        .map(PluginResponse::Queued)
+    }
+}
+
+#[rocket::async_trait]
+impl Handler for PluginHandler {
+    async fn handle<'r>(&self, req: &'r Request<'_>, data: Data<'r>) -> Outcome<'r> {
+        Outcome::from(req, self.ingest(req, data).await)
+    }
+}
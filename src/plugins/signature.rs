@@ -0,0 +1,30 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use sha2::Sha256;
+
+// constant time check of an HMAC-SHA256 signature over the concatenated payload parts
+pub fn verify_hmac_sha256(secret: &str, payload: &[&[u8]], signature: &[u8]) -> bool {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("hmac accepts keys of any length");
+    for part in payload {
+        mac.update(part);
+    }
+    mac.verify_slice(signature).is_ok()
+}
+
+// reject signed timestamps outside the tolerance to stop replayed requests
+pub fn timestamp_within(timestamp: &str, tolerance_secs: i64) -> bool {
+    match timestamp.trim().parse::<i64>() {
+        Ok(ts) => (Utc::now().timestamp() - ts).abs() <= tolerance_secs,
+        Err(_) => false,
+    }
+}
+
+// string value at a path of keys in a JSON body
+pub fn json_field(body: &[u8], path: &[&str]) -> Option<String> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    for key in path {
+        value = value.get_mut(*key)?.take();
+    }
+    value.as_str().map(String::from)
+}
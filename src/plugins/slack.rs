@@ -0,0 +1,157 @@
+use crate::plugins::signature::{json_field, timestamp_within, verify_hmac_sha256};
+use crate::plugins::WebhookPlugin;
+use config::Config;
+use log::warn;
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+// [plugins.slack] table in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SlackConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub secrets: Vec<String>, // app signing secrets, list several while rotating
+    #[serde(default = "default_tolerance")]
+    pub tolerance_secs: i64, // maximum age of X-Slack-Request-Timestamp
+}
+
+fn default_tolerance() -> i64 {
+    300
+}
+
+pub struct SlackPlugin {
+    config: SlackConfig,
+}
+
+impl SlackPlugin {
+    pub fn from_settings(settings: &Config) -> Option<SlackPlugin> {
+        let config = settings.get::<SlackConfig>("plugins.slack").ok()?;
+        if !config.enabled {
+            return None;
+        }
+        if config.secrets.is_empty() {
+            warn!("no slack secrets configured, every slack request will be rejected");
+        }
+        Some(SlackPlugin { config })
+    }
+}
+
+impl WebhookPlugin for SlackPlugin {
+    fn name(&self) -> &'static str {
+        "slack"
+    }
+
+    // X-Slack-Signature is "v0=" followed by the hex HMAC of "v0:<timestamp>:<body>"
+    fn verify(&self, headers: &HeaderMap<'_>, body: &[u8]) -> bool {
+        let Some(timestamp) = headers.get_one("x-slack-request-timestamp") else {
+            return false;
+        };
+        if !timestamp_within(timestamp, self.config.tolerance_secs) {
+            return false;
+        }
+        let Some(signature) = headers
+            .get_one("x-slack-signature")
+            .and_then(|h| h.trim().strip_prefix("v0="))
+            .and_then(|h| hex::decode(h).ok())
+        else {
+            return false;
+        };
+
+        self.config.secrets.iter().any(|secret| {
+            verify_hmac_sha256(
+                secret,
+                &[b"v0:", timestamp.as_bytes(), b":", body],
+                &signature,
+            )
+        })
+    }
+
+    // events api callbacks carry the actual event type one level down
+    fn event_type(&self, _headers: &HeaderMap<'_>, body: &[u8]) -> Option<String> {
+        match json_field(body, &["type"]).as_deref() {
+            Some("event_callback") => json_field(body, &["event", "type"]),
+            _ => json_field(body, &["type"]),
+        }
+    }
+
+    fn dedup_key(&self, _headers: &HeaderMap<'_>, body: &[u8]) -> Option<String> {
+        json_field(body, &["event_id"])
+    }
+
+    // the events api checks a new request url by expecting its challenge echoed back
+    fn immediate_response(&self, _headers: &HeaderMap<'_>, body: &[u8]) -> Option<Value> {
+        if json_field(body, &["type"]).as_deref() != Some("url_verification") {
+            return None;
+        }
+        json_field(body, &["challenge"]).map(|challenge| json!({ "challenge": challenge }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+
+    // example from Slack's "Verifying requests from Slack" documentation
+    const SECRET: &str = "8f742231b10e8888abcd99yyyzzz85a5";
+    const TIMESTAMP: &str = "1531420618";
+    const BODY: &[u8] = b"token=xyzz0WbapA4vBCDEFasx0q6G&team_id=T1DC2JH3J&team_domain=testteamnow&channel_id=G8PSS9T3V&channel_name=foobar&user_id=U2CERLKJA&user_name=roadrunner&command=%2Fwebhook-collect&text=&response_url=https%3A%2F%2Fhooks.slack.com%2Fcommands%2FT1DC2JH3J%2F397700885554%2F96rGlfmibIGlgcZRskXaIFfN&trigger_id=398738663015.47445629121.803a0bc887a14d10d2c447fce8b6703c";
+    const SIGNATURE: &str = "v0=a2114d57b48eac39b9ad189dd8316235a7b4a8d21a10bd27519666489c69b503";
+
+    // the documented timestamp is long past, so only a boundless tolerance accepts it
+    fn plugin(secret: &str, tolerance_secs: i64) -> SlackPlugin {
+        SlackPlugin {
+            config: SlackConfig {
+                enabled: true,
+                secrets: vec![secret.to_string()],
+                tolerance_secs,
+            },
+        }
+    }
+
+    fn headers(timestamp: &str, signature: &str) -> HeaderMap<'static> {
+        let mut headers = HeaderMap::new();
+        headers.add(Header::new(
+            "X-Slack-Request-Timestamp",
+            timestamp.to_string(),
+        ));
+        headers.add(Header::new("X-Slack-Signature", signature.to_string()));
+        headers
+    }
+
+    #[test]
+    fn accepts_the_documented_signature() {
+        let plugin = plugin(SECRET, i64::MAX);
+        assert!(plugin.verify(&headers(TIMESTAMP, SIGNATURE), BODY));
+    }
+
+    #[test]
+    fn rejects_stale_timestamps() {
+        let plugin = plugin(SECRET, 300);
+        assert!(!plugin.verify(&headers(TIMESTAMP, SIGNATURE), BODY));
+    }
+
+    #[test]
+    fn rejects_wrong_secrets_timestamps_and_bodies() {
+        assert!(!plugin("wrong", i64::MAX).verify(&headers(TIMESTAMP, SIGNATURE), BODY));
+        let plugin = plugin(SECRET, i64::MAX);
+        assert!(!plugin.verify(&headers("1531420619", SIGNATURE), BODY));
+        assert!(!plugin.verify(&headers(TIMESTAMP, SIGNATURE), b"token=other"));
+        let unprefixed = SIGNATURE.trim_start_matches("v0=");
+        assert!(!plugin.verify(&headers(TIMESTAMP, unprefixed), BODY));
+    }
+
+    #[test]
+    fn answers_url_verification_challenges_only() {
+        let plugin = plugin(SECRET, 300);
+        let challenge = br#"{"token":"t","challenge":"3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P","type":"url_verification"}"#;
+        assert_eq!(
+            plugin.immediate_response(&HeaderMap::new(), challenge),
+            Some(json!({ "challenge": "3eZbrw1aBm2rZgRNFdxV2595E9CY3gmdALWMmHkvFXO7tYXAYM8P" }))
+        );
+        let event = br#"{"type":"event_callback","event_id":"Ev1","event":{"type":"message"}}"#;
+        assert_eq!(plugin.immediate_response(&HeaderMap::new(), event), None);
+    }
+}
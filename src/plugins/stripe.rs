@@ -0,0 +1,137 @@
+use crate::plugins::signature::{json_field, timestamp_within, verify_hmac_sha256};
+use crate::plugins::WebhookPlugin;
+use config::Config;
+use log::warn;
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+
+// [plugins.stripe] table in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct StripeConfig {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub secrets: Vec<String>, // endpoint signing secrets, list several while rotating
+    #[serde(default = "default_tolerance")]
+    pub tolerance_secs: i64, // maximum age of the signed timestamp
+}
+
+fn default_tolerance() -> i64 {
+    300
+}
+
+pub struct StripePlugin {
+    config: StripeConfig,
+}
+
+impl StripePlugin {
+    pub fn from_settings(settings: &Config) -> Option<StripePlugin> {
+        let config = settings.get::<StripeConfig>("plugins.stripe").ok()?;
+        if !config.enabled {
+            return None;
+        }
+        if config.secrets.is_empty() {
+            warn!("no stripe secrets configured, every stripe webhook will be rejected");
+        }
+        Some(StripePlugin { config })
+    }
+}
+
+impl WebhookPlugin for StripePlugin {
+    fn name(&self) -> &'static str {
+        "stripe"
+    }
+
+    // Stripe-Signature is "t=<timestamp>,v1=<hex>[,v1=<hex>]", signed over "<timestamp>.<body>"
+    fn verify(&self, headers: &HeaderMap<'_>, body: &[u8]) -> bool {
+        let Some(header) = headers.get_one("stripe-signature") else {
+            return false;
+        };
+        let mut timestamp = None;
+        let mut signatures = vec![];
+        for part in header.split(',') {
+            match part.trim().split_once('=') {
+                Some(("t", value)) => timestamp = Some(value),
+                Some(("v1", value)) => signatures.extend(hex::decode(value).ok()),
+                _ => {}
+            }
+        }
+        let Some(timestamp) = timestamp else {
+            return false;
+        };
+        if !timestamp_within(timestamp, self.config.tolerance_secs) {
+            return false;
+        }
+
+        self.config.secrets.iter().any(|secret| {
+            signatures.iter().any(|signature| {
+                verify_hmac_sha256(secret, &[timestamp.as_bytes(), b".", body], signature)
+            })
+        })
+    }
+
+    fn event_type(&self, _headers: &HeaderMap<'_>, body: &[u8]) -> Option<String> {
+        json_field(body, &["type"])
+    }
+
+    fn dedup_key(&self, _headers: &HeaderMap<'_>, body: &[u8]) -> Option<String> {
+        json_field(body, &["id"])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rocket::http::Header;
+
+    // Stripe publishes no fixed example, this one follows its documented scheme:
+    // hex HMAC-SHA256 of "<t>.<body>" with the endpoint secret
+    const SECRET: &str = "whsec_test_secret";
+    const TIMESTAMP: &str = "1492774577";
+    const BODY: &[u8] =
+        br#"{"id":"evt_test_webhook","object":"event","type":"payment_intent.succeeded"}"#;
+    const SIGNATURE: &str = "add268875c77da0b0493b2ae7deba5dabc736b8c4b5a40755158e5471b4c3fe2";
+
+    fn plugin(secret: &str, tolerance_secs: i64) -> StripePlugin {
+        StripePlugin {
+            config: StripeConfig {
+                enabled: true,
+                secrets: vec![secret.to_string()],
+                tolerance_secs,
+            },
+        }
+    }
+
+    fn headers(value: String) -> HeaderMap<'static> {
+        let mut headers = HeaderMap::new();
+        headers.add(Header::new("Stripe-Signature", value));
+        headers
+    }
+
+    #[test]
+    fn accepts_any_matching_v1_signature() {
+        let plugin = plugin(SECRET, i64::MAX);
+        let header = format!("t={},v1={}", TIMESTAMP, SIGNATURE);
+        assert!(plugin.verify(&headers(header), BODY));
+        // secret rotation sends one v1 per secret, unknown schemes are ignored
+        let header = format!(
+            "t={},v1={},v0=abc,v1={}",
+            TIMESTAMP,
+            "00".repeat(32),
+            SIGNATURE
+        );
+        assert!(plugin.verify(&headers(header), BODY));
+    }
+
+    #[test]
+    fn rejects_stale_timestamps_and_wrong_signatures() {
+        let header = format!("t={},v1={}", TIMESTAMP, SIGNATURE);
+        assert!(!plugin(SECRET, 300).verify(&headers(header.clone()), BODY));
+        assert!(!plugin("whsec_other", i64::MAX).verify(&headers(header.clone()), BODY));
+        let plugin = plugin(SECRET, i64::MAX);
+        assert!(!plugin.verify(&headers(header), b"{}"));
+        assert!(!plugin.verify(&headers(format!("v1={}", SIGNATURE)), BODY));
+        let header = format!("t=1492774578,v1={}", SIGNATURE);
+        assert!(!plugin.verify(&headers(header), BODY));
+    }
+}
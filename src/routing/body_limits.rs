@@ -0,0 +1,50 @@
+use log::warn;
+use serde::Deserialize;
+use std::collections::HashMap;
+
+// largest body the requests table can take in one insert; MariaDB's default max_allowed_packet
+// is 16 MiB for the whole statement, so leave room for the headers and the rest of the row
+pub const STORAGE_MAX_BODY_BYTES: u64 = 15 * 1024 * 1024;
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 1024 * 1024;
+
+// [body_limits] and [routes.<name>.body_limits] tables in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BodyLimits {
+    #[serde(default)]
+    pub max_body_bytes: Option<u64>,
+    #[serde(default)]
+    pub content_types: HashMap<String, u64>, // keyed by media type, "image/*" matches any image
+}
+
+impl BodyLimits {
+    pub fn for_content_type(&self, content_type: Option<&str>) -> Option<u64> {
+        let media_type = content_type?.split(';').next()?.trim().to_lowercase();
+        let wildcard = format!("{}/*", media_type.split('/').next()?);
+        self.content_types
+            .iter()
+            .find(|(ct, _)| ct.eq_ignore_ascii_case(&media_type))
+            .or_else(|| {
+                self.content_types
+                    .iter()
+                    .find(|(ct, _)| ct.eq_ignore_ascii_case(&wildcard))
+            })
+            .map(|(_, limit)| *limit)
+    }
+
+    // limits the storage layer cannot hold are clamped when they are applied
+    pub fn warn_above_storage(&self, scope: &str) {
+        let limits = self
+            .max_body_bytes
+            .iter()
+            .chain(self.content_types.values());
+        if limits
+            .into_iter()
+            .any(|limit| *limit > STORAGE_MAX_BODY_BYTES)
+        {
+            warn!(
+                "{} body limits above {} bytes are clamped to what storage can hold",
+                scope, STORAGE_MAX_BODY_BYTES
+            );
+        }
+    }
+}
@@ -0,0 +1,34 @@
+use crate::dispatcher::batch::BatchConfig;
+use crate::dispatcher::circuit_breaker::CircuitBreakerConfig;
+use crate::dispatcher::http_client::ClientConfig;
+use crate::dispatcher::outcome::OutcomePolicy;
+use crate::dispatcher::throttle::AdaptiveConfig;
+use serde::Deserialize;
+
+// the destination built from the top level http_proto and http_dest settings
+pub const DEFAULT_DESTINATION: &str = "default";
+
+// [destinations.<name>] tables in config/Settings.toml
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct DestinationConfig {
+    #[serde(default)]
+    pub url: Option<String>, // base url such as "http://localhost:7780", the default destination uses http_proto and http_dest
+    #[serde(default)]
+    pub rate: Option<f64>, // requests per second, defaults to one per reque_interval
+    #[serde(default)]
+    pub burst: Option<u32>, // requests that may be sent back to back after an idle period
+    #[serde(default)]
+    pub max_concurrency: Option<usize>, // defaults to max_in_flight_per_destination
+    #[serde(default)]
+    pub timeout_secs: Option<u64>, // defaults to delivery_timeout_secs
+    #[serde(default)]
+    pub outcome: Option<OutcomePolicy>, // replaces the top level [outcome] table
+    #[serde(default)]
+    pub circuit_breaker: Option<CircuitBreakerConfig>, // replaces the top level [circuit_breaker] table
+    #[serde(default)]
+    pub adaptive: Option<AdaptiveConfig>, // adjust rate to the destination's latency and errors
+    #[serde(default)]
+    pub client: Option<ClientConfig>, // replaces the top level [http_client] table
+    #[serde(default)]
+    pub batch: Option<BatchConfig>, // send queued requests together to a bulk endpoint
+}
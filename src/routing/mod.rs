@@ -0,0 +1,6 @@
+pub mod body_limits;
+pub mod destinations;
+pub mod partition;
+pub mod priority;
+pub mod route_table;
+pub mod schedule;
@@ -0,0 +1,19 @@
+use sha2::{Digest, Sha256};
+
+// value at a dotted path such as "order.id" in a JSON body, strings and numbers only
+pub fn body_value(body: &[u8], path: &str) -> Option<String> {
+    let mut value: serde_json::Value = serde_json::from_slice(body).ok()?;
+    for key in path.split('.').filter(|k| !k.is_empty()) {
+        value = value.get_mut(key)?.take();
+    }
+    match value {
+        serde_json::Value::String(s) => Some(s),
+        serde_json::Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+// partitions are scoped to their route and hashed to fit the partition_key column
+pub fn partition_key(route: &str, value: &str) -> String {
+    hex::encode(Sha256::digest(format!("{}\n{}", route, value).as_bytes()))
+}
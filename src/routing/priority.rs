@@ -0,0 +1,32 @@
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+
+// requests are clamped to this range, higher values are delivered first
+pub const MIN_PRIORITY: i32 = -1000;
+pub const MAX_PRIORITY: i32 = 1000;
+
+// [[priority_rules]] entries in config/Settings.toml, the first matching rule wins
+#[derive(Debug, Clone, Deserialize)]
+pub struct PriorityRule {
+    pub header: String, // such as "X-Shopify-Topic" or a tenant header like "X-Shopify-Shop-Domain"
+    #[serde(default)]
+    pub values: Vec<String>, // case-insensitive, any value of the header matches when empty
+    #[serde(default)]
+    pub route: Option<String>, // only apply to requests matching this [routes.<name>] table
+    pub priority: i32,
+}
+
+impl PriorityRule {
+    pub fn matches(&self, route: Option<&str>, headers: &HeaderMap<'_>) -> bool {
+        if self.route.is_some() && self.route.as_deref() != route {
+            return false;
+        }
+        headers.get(&self.header).any(|value| {
+            self.values.is_empty() || self.values.iter().any(|v| v.eq_ignore_ascii_case(value))
+        })
+    }
+}
+
+pub fn clamp_priority(priority: i32) -> i32 {
+    priority.clamp(MIN_PRIORITY, MAX_PRIORITY)
+}
@@ -0,0 +1,303 @@
+use crate::fairings::de_construct_request::RRequest;
+use crate::routing::body_limits::{BodyLimits, DEFAULT_MAX_BODY_BYTES, STORAGE_MAX_BODY_BYTES};
+use crate::routing::destinations::{DestinationConfig, DEFAULT_DESTINATION};
+use crate::routing::partition::{body_value, partition_key};
+use crate::routing::priority::{clamp_priority, PriorityRule};
+use crate::routing::schedule::RouteSchedule;
+use log::warn;
+use rocket::http::HeaderMap;
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+
+// [routes.<name>] tables in config/Settings.toml
+#[derive(Debug, Clone, Deserialize)]
+pub struct RouteConfig {
+    #[serde(default)]
+    pub path: String, // ingress path prefix, or a glob when it contains "*", any path when empty
+    #[serde(default)]
+    pub host: Option<String>, // ingress host, "*.example.com" matches any subdomain
+    #[serde(default)]
+    pub methods: Vec<String>, // ingress methods, any method when empty
+    #[serde(default)]
+    pub headers: HashMap<String, String>, // required ingress header values, "*" only requires the header
+    #[serde(default)]
+    pub destination: Option<String>, // name of a [destinations.<name>] table, the default destination when none
+    #[serde(default)]
+    pub destinations: Vec<String>, // fan out to every one of these destinations instead
+    #[serde(default)]
+    pub method: Option<String>, // deliver with this method instead of the original one
+    #[serde(default)]
+    pub query_as_json_body: bool, // move query parameters into a JSON object body on delivery
+    #[serde(default)]
+    pub idempotency_header: Option<String>, // dedup on this header when Idempotency-Key is absent
+    #[serde(default)]
+    pub idempotency_window_secs: Option<u64>, // overrides the top level idempotency_window_secs
+    #[serde(default)]
+    pub body_limits: BodyLimits, // overrides the top level [body_limits]
+    #[serde(default)]
+    pub priority: Option<i32>, // default priority for requests on this route
+    #[serde(default)]
+    pub schedule: RouteSchedule, // default delivery time for requests on this route
+    #[serde(default)]
+    pub partition_header: Option<String>, // requests with the same value are delivered in order, one at a time
+    #[serde(default)]
+    pub partition_body_path: Option<String>, // or the value at this dotted path of a JSON body, such as "order.id"
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RouteTable {
+    routes: HashMap<String, RouteConfig>,
+    body_limits: BodyLimits,
+    priority_rules: Vec<PriorityRule>,
+}
+
+impl RouteTable {
+    pub fn new(
+        routes: HashMap<String, RouteConfig>,
+        body_limits: BodyLimits,
+        priority_rules: Vec<PriorityRule>,
+    ) -> RouteTable {
+        body_limits.warn_above_storage("default");
+        for (name, route) in routes.iter() {
+            route.body_limits.warn_above_storage(name);
+            route.schedule.warn_invalid(name);
+        }
+        RouteTable {
+            routes,
+            body_limits,
+            priority_rules,
+        }
+    }
+
+    // name of the most specific route matching the request; host first, then path, headers and methods
+    pub fn resolve(&self, request: &RRequest<'_>) -> Option<String> {
+        let path = request.uri.split('?').next().unwrap_or_default();
+        let host = request.host.split(':').next().unwrap_or_default();
+        self.routes
+            .iter()
+            .filter(|(_, route)| route.matches(&request.method, host, path, request))
+            .max_by_key(|(name, route)| (route.specificity(), std::cmp::Reverse(name.as_str())))
+            .map(|(name, _)| name.clone())
+    }
+
+    // point routes naming a destination that is not configured at the default destination,
+    // before fan-out lists are deduplicated
+    pub fn resolve_unknown_destinations(
+        &mut self,
+        destinations: &HashMap<String, DestinationConfig>,
+    ) {
+        for (name, route) in self.routes.iter_mut() {
+            for destination in route
+                .destinations
+                .iter_mut()
+                .chain(route.destination.as_mut())
+            {
+                if destination != DEFAULT_DESTINATION && !destinations.contains_key(destination) {
+                    warn!(
+                        "route {} points at destination {} which is not configured, using the default destination",
+                        name, destination
+                    );
+                    *destination = DEFAULT_DESTINATION.to_string();
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&RouteConfig> {
+        self.routes.get(name)
+    }
+
+    // most specific body limit for the route and content type, never more than storage can hold
+    pub fn body_limit(&self, route: Option<&str>, content_type: Option<&str>) -> u64 {
+        let route_limits = route
+            .and_then(|name| self.get(name))
+            .map(|r| &r.body_limits);
+        route_limits
+            .and_then(|l| l.for_content_type(content_type))
+            .or_else(|| route_limits.and_then(|l| l.max_body_bytes))
+            .or_else(|| self.body_limits.for_content_type(content_type))
+            .or(self.body_limits.max_body_bytes)
+            .unwrap_or(DEFAULT_MAX_BODY_BYTES)
+            .min(STORAGE_MAX_BODY_BYTES)
+    }
+
+    // priority from the first matching header rule, else the route default, else 0
+    pub fn priority(&self, route: Option<&str>, headers: &HeaderMap<'_>) -> i32 {
+        self.priority_rules
+            .iter()
+            .find(|rule| rule.matches(route, headers))
+            .map(|rule| rule.priority)
+            .or_else(|| {
+                route
+                    .and_then(|name| self.get(name))
+                    .and_then(|r| r.priority)
+            })
+            .map(clamp_priority)
+            .unwrap_or(0)
+    }
+}
+
+impl RouteConfig {
+    // partition of a request on this route, none when it may be delivered in any order
+    pub fn partition_key(
+        &self,
+        route: &str,
+        headers: &HeaderMap<'_>,
+        body: &[u8],
+    ) -> Option<String> {
+        let value = self
+            .partition_header
+            .as_deref()
+            .and_then(|header| headers.get_one(header))
+            .map(String::from)
+            .or_else(|| {
+                self.partition_body_path
+                    .as_deref()
+                    .and_then(|path| body_value(body, path))
+            })?;
+        Some(partition_key(route, &value))
+    }
+
+    // every destination a request on this route is delivered to, empty for the default destination
+    pub fn delivery_destinations(&self) -> Vec<String> {
+        let mut destinations = self.destinations.clone();
+        if destinations.is_empty() {
+            destinations.extend(self.destination.clone());
+        }
+        let mut seen = HashSet::new();
+        destinations.retain(|destination| seen.insert(destination.clone()));
+        destinations
+    }
+
+    fn matches(&self, method: &str, host: &str, path: &str, request: &RRequest<'_>) -> bool {
+        let host_matches = match self.host.as_deref() {
+            Some(pattern) => match pattern.strip_prefix("*.") {
+                Some(domain) => host
+                    .to_lowercase()
+                    .ends_with(&format!(".{}", domain.to_lowercase())),
+                None => host.eq_ignore_ascii_case(pattern),
+            },
+            None => true,
+        };
+        let path_matches = if self.path.contains('*') {
+            glob_matches(path, &self.path)
+        } else {
+            path_matches(path, &self.path)
+        };
+        let method_matches =
+            self.methods.is_empty() || self.methods.iter().any(|m| m.eq_ignore_ascii_case(method));
+        let headers_match = self.headers.iter().all(|(name, expected)| {
+            request
+                .headers
+                .get(name)
+                .any(|value| expected == "*" || value.eq_ignore_ascii_case(expected))
+        });
+        host_matches && path_matches && method_matches && headers_match
+    }
+
+    // exact hosts beat wildcard hosts, then longer literal paths, then more header and method conditions
+    fn specificity(&self) -> (u8, usize, usize, bool) {
+        let host = match self.host.as_deref() {
+            Some(pattern) if pattern.starts_with("*.") => 1,
+            Some(_) => 2,
+            None => 0,
+        };
+        let path = self.path.chars().filter(|c| *c != '*').count();
+        (host, path, self.headers.len(), !self.methods.is_empty())
+    }
+}
+
+// "*" matches within one path segment, "**" matches any number of whole segments
+fn glob_matches(path: &str, pattern: &str) -> bool {
+    let path: Vec<&str> = path.trim_start_matches('/').split('/').collect();
+    let pattern: Vec<&str> = pattern.trim_start_matches('/').split('/').collect();
+    segments_match(&path, &pattern)
+}
+
+fn segments_match(path: &[&str], pattern: &[&str]) -> bool {
+    match pattern.split_first() {
+        None => path.is_empty(),
+        Some((&"**", rest)) => (0..=path.len()).any(|skip| segments_match(&path[skip..], rest)),
+        Some((segment, rest)) => match path.split_first() {
+            Some((first, path_rest)) => {
+                segment_matches(first, segment) && segments_match(path_rest, rest)
+            }
+            None => false,
+        },
+    }
+}
+
+// wildcard match of one segment, "order-*" matches "order-42"
+fn segment_matches(segment: &str, pattern: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return segment == pattern;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !segment.starts_with(first) || segment.len() < first.len() + last.len() {
+        return false;
+    }
+    let mut rest = &segment[first.len()..segment.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    segment.ends_with(last)
+}
+
+// prefix match on whole path segments, "/plugins" matches "/plugins/shopify" but not "/pluginsx"
+fn path_matches(path: &str, prefix: &str) -> bool {
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with('/') || prefix.ends_with('/'),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn prefixes_match_whole_segments() {
+        assert!(path_matches("/plugins", "/plugins"));
+        assert!(path_matches("/plugins/shopify", "/plugins"));
+        assert!(path_matches("/plugins/shopify", "/plugins/"));
+        assert!(!path_matches("/pluginsx", "/plugins"));
+        assert!(!path_matches("/other", "/plugins"));
+    }
+
+    #[test]
+    fn segment_wildcards() {
+        assert!(segment_matches("order-42", "order-*"));
+        assert!(segment_matches("42-order", "*-order"));
+        assert!(segment_matches("a-order-b-42", "a-*-b-*"));
+        assert!(segment_matches("items", "*"));
+        assert!(segment_matches("items", "items"));
+        assert!(!segment_matches("item", "items"));
+        assert!(!segment_matches("refund-42", "order-*"));
+        assert!(!segment_matches("ab", "ab*ab"));
+        assert!(!segment_matches("a-b", "a-*-b"));
+    }
+
+    #[test]
+    fn single_star_matches_exactly_one_segment() {
+        assert!(glob_matches("/orders/42/items", "/orders/*/items"));
+        assert!(!glob_matches("/orders/items", "/orders/*/items"));
+        assert!(!glob_matches("/orders/42/7/items", "/orders/*/items"));
+        assert!(!glob_matches("/orders/42/items/1", "/orders/*/items"));
+    }
+
+    #[test]
+    fn double_star_matches_any_number_of_segments() {
+        assert!(glob_matches("/orders", "/orders/**"));
+        assert!(glob_matches("/orders/42", "/orders/**"));
+        assert!(glob_matches("/orders/42/items/1", "/orders/**"));
+        assert!(glob_matches("/orders/42/items/1", "/orders/**/1"));
+        assert!(glob_matches("/orders/1", "/orders/**/1"));
+        assert!(glob_matches("/a/b/c/shop-9/x", "/**/shop-*/x"));
+        assert!(!glob_matches("/refunds/42", "/orders/**"));
+        assert!(!glob_matches("/orders/42/items/2", "/orders/**/1"));
+    }
+}
@@ -0,0 +1,142 @@
+use chrono::{DateTime, Duration, NaiveTime, TimeZone, Utc};
+use log::warn;
+use serde::Deserialize;
+
+// [routes.<name>.schedule] table in config/Settings.toml, used when a request sets no delivery time itself
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct RouteSchedule {
+    #[serde(default)]
+    pub delay_secs: Option<u64>, // hold requests on this route for this long
+    #[serde(default)]
+    pub deliver_between: Option<[String; 2]>, // UTC "HH:MM" window such as ["22:00", "06:00"]
+}
+
+impl RouteSchedule {
+    // earliest delivery time for a request arriving now, none when it may be sent right away
+    pub fn deliver_at(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let delayed = now + Duration::seconds(self.delay_secs.unwrap_or(0) as i64);
+        let deliver_at = match self.window() {
+            Some((start, end)) => next_in_window(delayed, start, end),
+            None => delayed,
+        };
+        (deliver_at > now).then_some(deliver_at)
+    }
+
+    fn window(&self) -> Option<(NaiveTime, NaiveTime)> {
+        let [start, end] = self.deliver_between.as_ref()?;
+        let parse = |t: &str| NaiveTime::parse_from_str(t.trim(), "%H:%M").ok();
+        Some((parse(start)?, parse(end)?))
+    }
+
+    // invalid windows would otherwise be ignored silently
+    pub fn warn_invalid(&self, route: &str) {
+        if self.deliver_between.is_some() && self.window().is_none() {
+            warn!(
+                "route {} deliver_between must be two \"HH:MM\" times, ignoring it",
+                route
+            );
+        }
+    }
+}
+
+// deliver_at is stored in a TIMESTAMP column, which ends in January 2038
+pub fn latest_deliver_at() -> DateTime<Utc> {
+    Utc.timestamp_opt(i32::MAX as i64, 0).unwrap()
+}
+
+// the time itself when it falls inside the window, else the next start of the window,
+// windows where end is before start wrap past midnight
+fn next_in_window(at: DateTime<Utc>, start: NaiveTime, end: NaiveTime) -> DateTime<Utc> {
+    let time = at.time();
+    let inside = if start <= end {
+        time >= start && time < end
+    } else {
+        time >= start || time < end
+    };
+    if inside {
+        return at;
+    }
+    let start_today = at.date_naive().and_time(start).and_utc();
+    if start_today > at {
+        start_today
+    } else {
+        start_today + Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(&format!("2024-03-10T{}:00Z", time))
+            .unwrap()
+            .with_timezone(&Utc)
+    }
+
+    fn schedule(delay_secs: Option<u64>, window: Option<[&str; 2]>) -> RouteSchedule {
+        RouteSchedule {
+            delay_secs,
+            deliver_between: window.map(|[start, end]| [start.to_string(), end.to_string()]),
+        }
+    }
+
+    #[test]
+    fn sends_right_away_without_delay_or_window() {
+        assert_eq!(schedule(None, None).deliver_at(at("12:00")), None);
+        assert_eq!(schedule(Some(0), None).deliver_at(at("12:00")), None);
+    }
+
+    #[test]
+    fn holds_for_the_delay() {
+        assert_eq!(
+            schedule(Some(90), None).deliver_at(at("12:00")),
+            Some(at("12:01") + Duration::seconds(30))
+        );
+    }
+
+    #[test]
+    fn waits_for_a_window_within_the_day() {
+        let daytime = schedule(None, Some(["09:00", "17:00"]));
+        assert_eq!(daytime.deliver_at(at("08:59")), Some(at("09:00")));
+        assert_eq!(daytime.deliver_at(at("09:00")), None);
+        assert_eq!(daytime.deliver_at(at("16:59")), None);
+        assert_eq!(
+            daytime.deliver_at(at("17:00")),
+            Some(at("09:00") + Duration::days(1))
+        );
+    }
+
+    #[test]
+    fn waits_for_a_window_wrapping_past_midnight() {
+        let nightly = schedule(None, Some(["22:00", "06:00"]));
+        assert_eq!(nightly.deliver_at(at("23:30")), None);
+        assert_eq!(nightly.deliver_at(at("00:00")), None);
+        assert_eq!(nightly.deliver_at(at("05:59")), None);
+        assert_eq!(nightly.deliver_at(at("06:00")), Some(at("22:00")));
+        assert_eq!(nightly.deliver_at(at("12:00")), Some(at("22:00")));
+        assert_eq!(nightly.deliver_at(at("22:00")), None);
+    }
+
+    #[test]
+    fn applies_the_window_after_the_delay() {
+        let delayed = schedule(Some(2 * 3600), Some(["22:00", "06:00"]));
+        assert_eq!(delayed.deliver_at(at("05:00")), Some(at("22:00")));
+        assert_eq!(delayed.deliver_at(at("21:00")), Some(at("23:00")));
+    }
+
+    #[test]
+    fn ignores_invalid_windows() {
+        let invalid = schedule(Some(60), Some(["22:00", "6am"]));
+        assert_eq!(invalid.window(), None);
+        assert_eq!(invalid.deliver_at(at("12:00")), Some(at("12:01")));
+    }
+
+    #[test]
+    fn latest_deliver_at_is_the_end_of_timestamp() {
+        assert_eq!(
+            latest_deliver_at().to_rfc3339(),
+            "2038-01-19T03:14:07+00:00"
+        );
+    }
+}